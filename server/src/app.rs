@@ -1,35 +1,153 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use crate::providers::TranslateProvider;
+use crate::{
+    cache::{DiskCache, MemoryCache, TieredCache, TranslationCache},
+    error::ApiError,
+    glossary::GlossaryStore,
+    jobs::JobStore,
+    memory::TranslationMemory,
+    providers::{chain::ProviderChain, prompt::PromptTemplates, TranslateProvider},
+    usage::UsageTracker,
+};
 
 #[derive(Clone)]
 pub struct AppState {
-    pub provider: Arc<dyn TranslateProvider>,
-    pub cache: moka::future::Cache<String, String>,
+    /// The translate provider chain, or why none could be configured; the
+    /// translation endpoints are disabled in that case.
+    pub providers: Result<Arc<ProviderChain>, Arc<str>>,
+    pub cache: Arc<dyn TranslationCache>,
+    pub glossaries: Arc<GlossaryStore>,
+    pub jobs: Arc<JobStore>,
+    /// `None` when the translation memory is disabled.
+    pub memory: Option<Arc<TranslationMemory>>,
+    pub usage: Arc<UsageTracker>,
+    /// `None` disables the admin API.
+    pub admin_token: Option<Arc<str>>,
 }
 
 impl AppState {
     pub fn new(
-        provider: Arc<dyn TranslateProvider>,
-        cache: moka::future::Cache<String, String>,
+        providers: Result<ProviderChain, String>,
+        cache: Arc<dyn TranslationCache>,
+        glossaries: GlossaryStore,
+        jobs: JobStore,
+        memory: Option<TranslationMemory>,
+        usage: UsageTracker,
+        admin_token: Option<String>,
     ) -> Self {
-        Self { provider, cache }
+        Self {
+            providers: providers.map(Arc::new).map_err(Arc::from),
+            cache,
+            glossaries: Arc::new(glossaries),
+            jobs: Arc::new(jobs),
+            memory: memory.map(Arc::new),
+            usage: Arc::new(usage),
+            admin_token: admin_token.map(Arc::from),
+        }
+    }
+
+    /// The provider chain, or `ProviderNotConfigured` when translation is
+    /// disabled.
+    pub fn translator(&self) -> Result<&ProviderChain, ApiError> {
+        self.providers
+            .as_deref()
+            .map_err(|reason| ApiError::ProviderNotConfigured(reason.to_string()))
     }
 }
 
-pub fn build_provider() -> anyhow::Result<Arc<dyn TranslateProvider>> {
+/// `TRANSLATE_PROVIDER` is a comma-separated list tried in order, e.g.
+/// `deepseek,openai_compat`.
+pub fn build_providers() -> Result<ProviderChain, ProviderSetupError> {
     let which = std::env::var("TRANSLATE_PROVIDER").unwrap_or_else(|_| "deepseek".to_string());
-    match which.as_str() {
+    let prompts = Arc::new(build_prompts()?);
+    let providers = which
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| build_provider(name, prompts.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if providers.is_empty() {
+        return Err(ProviderSetupError::NotConfigured(
+            "TRANSLATE_PROVIDER is empty".into(),
+        ));
+    }
+
+    let failure_threshold: u32 = std::env::var("PROVIDER_FAILURE_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3);
+    let cooldown_secs: u64 = std::env::var("PROVIDER_COOLDOWN_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    Ok(ProviderChain::new(
+        providers,
+        failure_threshold,
+        Duration::from_secs(cooldown_secs),
+    ))
+}
+
+/// Why `build_providers` returned no chain.
+#[derive(thiserror::Error, Debug)]
+pub enum ProviderSetupError {
+    /// No provider selected, or one without its key or endpoint: the
+    /// server starts with translation disabled.
+    #[error("{0}")]
+    NotConfigured(String),
+    /// Settings that are present but wrong; start-up fails.
+    #[error(transparent)]
+    Invalid(#[from] anyhow::Error),
+}
+
+fn build_provider(
+    which: &str,
+    prompts: Arc<PromptTemplates>,
+) -> Result<Arc<dyn TranslateProvider>, ProviderSetupError> {
+    let not_configured =
+        |what: &str| ProviderSetupError::NotConfigured(format!("missing {}", what));
+    match which {
         "deepseek" => {
-            let provider = crate::providers::deepseek::from_env()
-                .ok_or_else(|| anyhow::anyhow!("missing DEEPSEEK_API_KEY"))?;
+            let provider = crate::providers::deepseek::from_env(prompts)?
+                .ok_or_else(|| not_configured("DEEPSEEK_API_KEY"))?;
+            Ok(Arc::new(provider))
+        }
+        "openai_compat" => {
+            let provider = crate::providers::openai_compat::from_env(prompts)?
+                .ok_or_else(|| not_configured("OPENAI_COMPAT_BASE_URL"))?;
             Ok(Arc::new(provider))
         }
-        other => Err(anyhow::anyhow!(format!("unsupported provider: {}", other))),
+        "dictionary" => Ok(Arc::new(crate::providers::dictionary::from_env()?)),
+        "mock" => Ok(Arc::new(crate::providers::mock::from_env()?)),
+        other => Err(anyhow::anyhow!(format!("unsupported provider: {}", other)).into()),
+    }
+}
+
+/// Built-in prompt templates, overridden by the files in `PROMPT_DIR` when
+/// it is set; see `providers::prompt`.
+fn build_prompts() -> anyhow::Result<PromptTemplates> {
+    match std::env::var("PROMPT_DIR") {
+        Ok(dir) if !dir.trim().is_empty() => PromptTemplates::load(std::path::Path::new(&dir))
+            .map_err(|e| anyhow::anyhow!("load prompt templates: {}", e)),
+        _ => Ok(PromptTemplates::default()),
     }
 }
 
-pub fn build_cache() -> moka::future::Cache<String, String> {
+/// `CACHE_BACKEND` selects `memory` (default), `disk` or `tiered` (memory in
+/// front of disk). The memory tier uses `CACHE_TTL_SECONDS` and
+/// `CACHE_MAX_ENTRIES`; the disk tier lives at `CACHE_DISK_PATH` and keeps
+/// entries for `CACHE_DISK_TTL_SECONDS` (0 = forever).
+pub fn build_cache() -> anyhow::Result<Arc<dyn TranslationCache>> {
+    let backend = std::env::var("CACHE_BACKEND").unwrap_or_else(|_| "memory".to_string());
+    let cache: Arc<dyn TranslationCache> = match backend.trim() {
+        "memory" => Arc::new(build_memory_cache()),
+        "disk" => Arc::new(build_disk_cache()?),
+        "tiered" => Arc::new(TieredCache::new(build_memory_cache(), build_disk_cache()?)),
+        other => return Err(anyhow::anyhow!("unsupported cache backend: {}", other)),
+    };
+    Ok(cache)
+}
+
+fn build_memory_cache() -> MemoryCache {
     let ttl_secs: u64 = std::env::var("CACHE_TTL_SECONDS")
         .ok()
         .and_then(|v| v.parse().ok())
@@ -38,8 +156,106 @@ pub fn build_cache() -> moka::future::Cache<String, String> {
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(10_000);
-    moka::future::Cache::builder()
-        .time_to_live(std::time::Duration::from_secs(ttl_secs))
-        .max_capacity(max_entries)
-        .build()
+    MemoryCache::new(Duration::from_secs(ttl_secs), max_entries)
+}
+
+fn build_disk_cache() -> anyhow::Result<DiskCache> {
+    let path = std::env::var("CACHE_DISK_PATH")
+        .unwrap_or_else(|_| "data/translate_cache.redb".to_string());
+    let ttl_secs: u64 = std::env::var("CACHE_DISK_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(7 * 24 * 3600);
+    let ttl = (ttl_secs > 0).then(|| Duration::from_secs(ttl_secs));
+    DiskCache::open(std::path::Path::new(&path), ttl)
+        .map_err(|e| anyhow::anyhow!("open disk cache {}: {}", path, e))
+}
+
+/// Loads glossaries from the JSON file at `GLOSSARY_PATH`; none are
+/// configured when the variable is unset.
+pub fn build_glossaries() -> anyhow::Result<GlossaryStore> {
+    match std::env::var("GLOSSARY_PATH") {
+        Ok(path) => {
+            let raw = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("read {}: {}", path, e))?;
+            GlossaryStore::from_json(&raw).map_err(|e| anyhow::anyhow!("parse {}: {}", path, e))
+        }
+        Err(_) => Ok(GlossaryStore::default()),
+    }
+}
+
+/// Opens the SQLite translation memory at `TM_PATH`
+/// (default `data/translation_memory.sqlite`); an empty value disables it.
+pub fn build_memory() -> anyhow::Result<Option<TranslationMemory>> {
+    let path =
+        std::env::var("TM_PATH").unwrap_or_else(|_| "data/translation_memory.sqlite".to_string());
+    if path.trim().is_empty() {
+        return Ok(None);
+    }
+    TranslationMemory::open(std::path::Path::new(&path))
+        .map(Some)
+        .map_err(|e| anyhow::anyhow!("open translation memory {}: {}", path, e))
+}
+
+/// Limits for uploaded document jobs: `DOCUMENT_MAX_BYTES` per upload,
+/// `DOCUMENT_MAX_RUNNING` jobs translating at once,
+/// `DOCUMENT_MAX_PENDING` jobs queued or running in total and
+/// `DOCUMENT_JOB_TTL_SECONDS` to keep finished results.
+pub fn build_jobs() -> JobStore {
+    let max_bytes: usize = std::env::var("DOCUMENT_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10 * 1024 * 1024);
+    let max_running: usize = std::env::var("DOCUMENT_MAX_RUNNING")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2);
+    let max_pending: usize = std::env::var("DOCUMENT_MAX_PENDING")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(20);
+    let ttl_secs: u64 = std::env::var("DOCUMENT_JOB_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3600);
+    JobStore::new(
+        Duration::from_secs(ttl_secs),
+        max_running,
+        max_pending,
+        max_bytes,
+    )
+}
+
+/// Daily token budgets: `USAGE_DAILY_TOKEN_BUDGET` for all callers together
+/// and `USAGE_DAILY_TOKEN_BUDGET_PER_KEY` for each API key. Unset or 0 means
+/// unlimited. Only the comma-separated keys in `USAGE_API_KEYS` are
+/// accounted on their own; other keys count as `anonymous`.
+pub fn build_usage() -> UsageTracker {
+    let budget = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|&v| v > 0)
+    };
+    UsageTracker::new(
+        budget("USAGE_DAILY_TOKEN_BUDGET"),
+        budget("USAGE_DAILY_TOKEN_BUDGET_PER_KEY"),
+    )
+    .with_api_keys(
+        std::env::var("USAGE_API_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(str::to_string),
+    )
+}
+
+/// Bearer token for `/api/admin/*`; the admin API is disabled when
+/// `ADMIN_TOKEN` is unset or empty.
+pub fn build_admin_token() -> Option<String> {
+    std::env::var("ADMIN_TOKEN")
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
 }
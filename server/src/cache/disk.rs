@@ -0,0 +1,184 @@
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use redb::{Database, ReadableTableMetadata, TableDefinition};
+use tracing::{info, warn};
+
+use super::{CacheStats, Counters, TranslationCache};
+
+/// Key → (expires at in unix seconds, 0 = never; translation).
+const TABLE: TableDefinition<&str, (u64, &str)> = TableDefinition::new("translations");
+
+/// Embedded key-value file (redb), so cached translations survive restarts.
+/// Expired entries are skipped on read and dropped when the file is opened.
+pub struct DiskCache {
+    db: Arc<Database>,
+    /// `None` keeps entries forever.
+    ttl: Option<Duration>,
+    counters: Counters,
+}
+
+impl DiskCache {
+    pub fn open(path: &Path, ttl: Option<Duration>) -> anyhow::Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let db = Database::create(path)?;
+        let now = unix_now();
+        let (kept, purged) = retain(&db, |_, expires_at| expires_at == 0 || expires_at > now)?;
+        info!(path = %path.display(), entries = kept, purged, "disk cache opened");
+        Ok(Self {
+            db: Arc::new(db),
+            ttl,
+            counters: Counters::default(),
+        })
+    }
+
+    /// Runs `f` on the blocking pool; redb calls block on file I/O.
+    async fn with_db<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> anyhow::Result<T> + Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db)).await?
+    }
+}
+
+#[async_trait]
+impl TranslationCache for DiskCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        let key = key.to_string();
+        let outcome = self
+            .with_db(move |db| {
+                let tx = db.begin_read()?;
+                let table = tx.open_table(TABLE)?;
+                let Some(entry) = table.get(key.as_str())? else {
+                    return Ok(None);
+                };
+                let (expires_at, value) = entry.value();
+                if expires_at != 0 && expires_at <= unix_now() {
+                    return Ok(None);
+                }
+                Ok(Some(value.to_string()))
+            })
+            .await;
+        let value = outcome.unwrap_or_else(|err| {
+            warn!(error = %err, "disk cache read failed");
+            None
+        });
+        self.counters.record(value)
+    }
+
+    async fn insert(&self, key: String, value: String) {
+        let expires_at = self.ttl.map_or(0, |ttl| unix_now() + ttl.as_secs().max(1));
+        let outcome = self
+            .with_db(move |db| {
+                let tx = db.begin_write()?;
+                tx.open_table(TABLE)?
+                    .insert(key.as_str(), (expires_at, value.as_str()))?;
+                tx.commit()?;
+                Ok(())
+            })
+            .await;
+        if let Err(err) = outcome {
+            warn!(error = %err, "disk cache write failed");
+        }
+    }
+
+    async fn remove(&self, key: &str) -> bool {
+        let key = key.to_string();
+        let outcome = self
+            .with_db(move |db| {
+                let tx = db.begin_write()?;
+                let removed = tx.open_table(TABLE)?.remove(key.as_str())?.is_some();
+                tx.commit()?;
+                Ok(removed)
+            })
+            .await;
+        outcome.unwrap_or_else(|err| {
+            warn!(error = %err, "disk cache remove failed");
+            false
+        })
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> u64 {
+        let prefix = prefix.to_string();
+        let outcome = self
+            .with_db(move |db| retain(db, |key, _| !key.starts_with(&prefix)))
+            .await;
+        match outcome {
+            Ok((_, removed)) => removed,
+            Err(err) => {
+                warn!(error = %err, "disk cache remove failed");
+                0
+            }
+        }
+    }
+
+    async fn stats(&self) -> CacheStats {
+        let entries = self
+            .with_db(|db| {
+                let tx = db.begin_read()?;
+                Ok(tx.open_table(TABLE)?.len()?)
+            })
+            .await;
+        CacheStats {
+            disk_entries: entries.ok(),
+            ..self.counters.stats()
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "disk"
+    }
+}
+
+/// Keeps the entries `keep(key, expires_at)` accepts; returns how many were
+/// kept and removed. Also creates the table on first use.
+fn retain(db: &Database, keep: impl Fn(&str, u64) -> bool) -> anyhow::Result<(u64, u64)> {
+    let tx = db.begin_write()?;
+    let counts = {
+        let mut table = tx.open_table(TABLE)?;
+        let before = table.len()?;
+        table.retain(|key, (expires_at, _)| keep(key, expires_at))?;
+        let kept = table.len()?;
+        (kept, before - kept)
+    };
+    tx.commit()?;
+    Ok(counts)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn entries_survive_reopening() {
+        let path = std::env::temp_dir().join(format!("disk-cache-{}.redb", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let cache = DiskCache::open(&path, None).unwrap();
+        cache.insert("p:kept".into(), "Hallo".into()).await;
+        cache.insert("p:gone".into(), "weg".into()).await;
+        assert!(cache.remove("p:gone").await);
+        drop(cache);
+
+        let reopened = DiskCache::open(&path, None).unwrap();
+        assert_eq!(reopened.get("p:kept").await.as_deref(), Some("Hallo"));
+        assert_eq!(reopened.get("p:gone").await, None);
+        assert_eq!(reopened.stats().await.disk_entries, Some(1));
+        drop(reopened);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use super::{CacheStats, Counters, TranslationCache};
+
+/// In-process moka cache; lost on restart.
+pub struct MemoryCache {
+    inner: moka::future::Cache<String, String>,
+    counters: Counters,
+    /// Key and value bytes currently held, kept in step by the eviction
+    /// listener.
+    bytes: Arc<AtomicU64>,
+}
+
+impl MemoryCache {
+    pub fn new(ttl: Duration, max_entries: u64) -> Self {
+        let bytes = Arc::new(AtomicU64::new(0));
+        let released = bytes.clone();
+        Self {
+            inner: moka::future::Cache::builder()
+                .time_to_live(ttl)
+                .max_capacity(max_entries)
+                .eviction_listener(move |key: Arc<String>, value: String, _| {
+                    released.fetch_sub(weight(&key, &value), Ordering::Relaxed);
+                })
+                .build(),
+            counters: Counters::default(),
+            bytes,
+        }
+    }
+}
+
+#[async_trait]
+impl TranslationCache for MemoryCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        self.counters.record(self.inner.get(key).await)
+    }
+
+    async fn insert(&self, key: String, value: String) {
+        self.bytes
+            .fetch_add(weight(&key, &value), Ordering::Relaxed);
+        self.inner.insert(key, value).await;
+    }
+
+    async fn remove(&self, key: &str) -> bool {
+        self.inner.remove(key).await.is_some()
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> u64 {
+        let keys: Vec<Arc<String>> = self
+            .inner
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key)
+            .collect();
+        let mut removed = 0;
+        for key in keys {
+            if self.inner.remove(key.as_str()).await.is_some() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    async fn stats(&self) -> CacheStats {
+        self.inner.run_pending_tasks().await;
+        CacheStats {
+            memory_entries: Some(self.inner.entry_count()),
+            memory_bytes: Some(self.bytes.load(Ordering::Relaxed)),
+            ..self.counters.stats()
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+}
+
+fn weight(key: &str, value: &str) -> u64 {
+    (key.len() + value.len()) as u64
+}
@@ -0,0 +1,70 @@
+//! Translation cache backends. Keys are the digests built by the translate
+//! routes, prefixed with the provider name; values are translations.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+pub mod disk;
+pub mod memory;
+pub mod tiered;
+
+pub use disk::DiskCache;
+pub use memory::MemoryCache;
+pub use tiered::TieredCache;
+
+#[async_trait]
+pub trait TranslationCache: Send + Sync {
+    async fn get(&self, key: &str) -> Option<String>;
+    async fn insert(&self, key: String, value: String);
+    /// Returns whether the key was present.
+    async fn remove(&self, key: &str) -> bool;
+    /// Removes every entry whose key starts with `prefix`; returns how many.
+    async fn remove_prefix(&self, prefix: &str) -> u64;
+    async fn stats(&self) -> CacheStats;
+    /// Backend name, as configured by `CACHE_BACKEND`.
+    fn name(&self) -> &'static str;
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    /// Lookups since start-up.
+    pub hits: u64,
+    pub misses: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_entries: Option<u64>,
+    /// Approximate bytes of keys and values held by the memory tier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_entries: Option<u64>,
+}
+
+/// Hit and miss counters shared by the backends.
+#[derive(Default)]
+pub struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Counters {
+    pub fn record<T>(&self, lookup: Option<T>) -> Option<T> {
+        let counter = if lookup.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        lookup
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            ..CacheStats::default()
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use async_trait::async_trait;
+
+use super::{CacheStats, Counters, DiskCache, MemoryCache, TranslationCache};
+
+/// Memory in front of disk: hits are served from memory, disk hits are
+/// promoted into memory, and writes go to both.
+pub struct TieredCache {
+    memory: MemoryCache,
+    disk: DiskCache,
+    counters: Counters,
+}
+
+impl TieredCache {
+    pub fn new(memory: MemoryCache, disk: DiskCache) -> Self {
+        Self {
+            memory,
+            disk,
+            counters: Counters::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl TranslationCache for TieredCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        if let Some(value) = self.memory.get(key).await {
+            return self.counters.record(Some(value));
+        }
+        let value = self.counters.record(self.disk.get(key).await)?;
+        self.memory.insert(key.to_string(), value.clone()).await;
+        Some(value)
+    }
+
+    async fn insert(&self, key: String, value: String) {
+        self.disk.insert(key.clone(), value.clone()).await;
+        self.memory.insert(key, value).await;
+    }
+
+    async fn remove(&self, key: &str) -> bool {
+        let in_memory = self.memory.remove(key).await;
+        self.disk.remove(key).await || in_memory
+    }
+
+    /// Counts the disk tier, which holds every entry the memory tier does.
+    async fn remove_prefix(&self, prefix: &str) -> u64 {
+        let in_memory = self.memory.remove_prefix(prefix).await;
+        self.disk.remove_prefix(prefix).await.max(in_memory)
+    }
+
+    async fn stats(&self) -> CacheStats {
+        let memory = self.memory.stats().await;
+        let disk = self.disk.stats().await;
+        CacheStats {
+            memory_entries: memory.memory_entries,
+            memory_bytes: memory.memory_bytes,
+            disk_entries: disk.disk_entries,
+            ..self.counters.stats()
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "tiered"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn disk_hits_are_promoted_into_memory() {
+        let path = std::env::temp_dir().join(format!("tiered-cache-{}.redb", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let disk = DiskCache::open(&path, None).unwrap();
+        disk.insert("p:key".into(), "Hallo".into()).await;
+
+        let cache = TieredCache::new(MemoryCache::new(Duration::from_secs(60), 100), disk);
+        assert_eq!(cache.memory.get("p:key").await, None);
+        assert_eq!(cache.get("p:key").await.as_deref(), Some("Hallo"));
+        assert_eq!(cache.memory.get("p:key").await.as_deref(), Some("Hallo"));
+
+        // Served from memory once the disk tier no longer has it.
+        assert!(cache.disk.remove("p:key").await);
+        assert_eq!(cache.get("p:key").await.as_deref(), Some("Hallo"));
+        let stats = cache.stats().await;
+        assert_eq!((stats.hits, stats.misses), (2, 0));
+
+        drop(cache);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+//! Offline language detection.
+//!
+//! A script census decides the writing system first (Han, Kana, Hangul,
+//! Cyrillic, ...). Languages that share the Latin script are then told apart
+//! by a character-trigram model trained at startup from the small corpora in
+//! `corpus/`, which are compiled into the binary.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::OnceLock,
+};
+
+use crate::providers::Lang;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Script {
+    Han,
+    Kana,
+    Hangul,
+    Latin,
+    Cyrillic,
+    Arabic,
+    Thai,
+}
+
+impl Script {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Script::Han => "Han",
+            Script::Kana => "Kana",
+            Script::Hangul => "Hangul",
+            Script::Latin => "Latin",
+            Script::Cyrillic => "Cyrillic",
+            Script::Arabic => "Arabic",
+            Script::Thai => "Thai",
+        }
+    }
+
+    pub(crate) fn of(c: char) -> Option<Script> {
+        let script = match c as u32 {
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0xF900..=0xFAFF | 0x2E80..=0x2FDF => Script::Han,
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9D => Script::Kana,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF | 0x3130..=0x318F => Script::Hangul,
+            0x0400..=0x052F => Script::Cyrillic,
+            0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+            0x0E00..=0x0E7F => Script::Thai,
+            0x0041..=0x005A | 0x0061..=0x007A | 0x00C0..=0x024F | 0x1E00..=0x1EFF
+                if c.is_alphabetic() =>
+            {
+                Script::Latin
+            }
+            _ => return None,
+        };
+        Some(script)
+    }
+
+    /// Ideographic and syllabic characters carry far more information than a
+    /// single letter, so they count double in the census.
+    fn weight(&self) -> f32 {
+        match self {
+            Script::Han | Script::Kana | Script::Hangul => 2.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Candidate {
+    pub lang: Lang,
+    pub confidence: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Detection {
+    /// Dominant script of the input, `None` when it has no letters at all.
+    pub script: Option<Script>,
+    /// Candidate languages, most likely first.
+    pub candidates: Vec<Candidate>,
+}
+
+impl Detection {
+    pub fn top(&self) -> Option<Candidate> {
+        self.candidates.first().copied()
+    }
+}
+
+/// Candidates below this confidence are dropped from the ranking.
+const MIN_CONFIDENCE: f32 = 0.01;
+
+/// Share of kana among Han+kana above which Han characters are read as
+/// Japanese kanji rather than Chinese.
+const KANA_SHARE_FOR_JA: f32 = 0.1;
+
+pub fn detect(text: &str) -> Detection {
+    let mut census: HashMap<Script, f32> = HashMap::new();
+    for script in text.chars().filter_map(Script::of) {
+        *census.entry(script).or_default() += script.weight();
+    }
+
+    let total: f32 = census.values().sum();
+    if total == 0.0 {
+        return Detection {
+            script: None,
+            candidates: Vec::new(),
+        };
+    }
+
+    let weight = |script| census.get(&script).copied().unwrap_or_default();
+    let mut scores: HashMap<Lang, f32> = HashMap::new();
+
+    let han = weight(Script::Han);
+    let kana = weight(Script::Kana);
+    if kana > 0.0 && kana / (han + kana) >= KANA_SHARE_FOR_JA {
+        *scores.entry(Lang::Ja).or_default() += han + kana;
+    } else {
+        *scores.entry(Lang::Zh).or_default() += han;
+        *scores.entry(Lang::Ja).or_default() += kana;
+    }
+    *scores.entry(Lang::Ko).or_default() += weight(Script::Hangul);
+    *scores.entry(Lang::Ru).or_default() += weight(Script::Cyrillic);
+    *scores.entry(Lang::Ar).or_default() += weight(Script::Arabic);
+    *scores.entry(Lang::Th).or_default() += weight(Script::Thai);
+
+    let latin = weight(Script::Latin);
+    if latin > 0.0 {
+        for (lang, p) in latin_model().classify(text) {
+            *scores.entry(lang).or_default() += latin * p;
+        }
+    }
+
+    let mut candidates: Vec<Candidate> = scores
+        .into_iter()
+        .map(|(lang, score)| Candidate {
+            lang,
+            confidence: score / total,
+        })
+        .filter(|c| c.confidence >= MIN_CONFIDENCE)
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let script = census
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(script, _)| script);
+
+    Detection { script, candidates }
+}
+
+const LATIN_CORPORA: [(Lang, &str); 7] = [
+    (Lang::En, include_str!("corpus/en.txt")),
+    (Lang::De, include_str!("corpus/de.txt")),
+    (Lang::Fr, include_str!("corpus/fr.txt")),
+    (Lang::Es, include_str!("corpus/es.txt")),
+    (Lang::It, include_str!("corpus/it.txt")),
+    (Lang::Pt, include_str!("corpus/pt.txt")),
+    (Lang::Vi, include_str!("corpus/vi.txt")),
+];
+
+/// Number of trigrams after which additional evidence stops sharpening the
+/// distribution; keeps confidence honest for long inputs.
+const MAX_EVIDENCE: f32 = 12.0;
+
+struct Profile {
+    lang: Lang,
+    counts: HashMap<String, u32>,
+    total: u32,
+}
+
+struct TrigramModel {
+    profiles: Vec<Profile>,
+    vocabulary: usize,
+}
+
+fn latin_model() -> &'static TrigramModel {
+    static MODEL: OnceLock<TrigramModel> = OnceLock::new();
+    MODEL.get_or_init(TrigramModel::train)
+}
+
+impl TrigramModel {
+    fn train() -> Self {
+        let mut vocabulary: HashSet<String> = HashSet::new();
+        let profiles = LATIN_CORPORA
+            .iter()
+            .map(|(lang, corpus)| {
+                let mut counts: HashMap<String, u32> = HashMap::new();
+                for tri in trigrams(corpus) {
+                    vocabulary.insert(tri.clone());
+                    *counts.entry(tri).or_default() += 1;
+                }
+                let total = counts.values().sum();
+                Profile {
+                    lang: *lang,
+                    counts,
+                    total,
+                }
+            })
+            .collect();
+        Self {
+            profiles,
+            vocabulary: vocabulary.len(),
+        }
+    }
+
+    /// Naive Bayes over trigrams with add-one smoothing. The log-likelihoods
+    /// are averaged and rescaled by a capped evidence count before the
+    /// softmax, so a three-word snippet is not reported as 100% certain.
+    fn classify(&self, text: &str) -> Vec<(Lang, f32)> {
+        let grams = trigrams(text);
+        if grams.is_empty() {
+            return Vec::new();
+        }
+        let evidence = (grams.len() as f32).min(MAX_EVIDENCE);
+
+        let scores: Vec<(Lang, f32)> = self
+            .profiles
+            .iter()
+            .map(|profile| {
+                let denom = (profile.total as usize + self.vocabulary) as f32;
+                let log_likelihood: f32 = grams
+                    .iter()
+                    .map(|tri| {
+                        let count = profile.counts.get(tri).copied().unwrap_or_default();
+                        ((count + 1) as f32 / denom).ln()
+                    })
+                    .sum();
+                (profile.lang, log_likelihood / grams.len() as f32 * evidence)
+            })
+            .collect();
+
+        let max = scores
+            .iter()
+            .map(|(_, s)| *s)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let exp: Vec<(Lang, f32)> = scores
+            .into_iter()
+            .map(|(lang, s)| (lang, (s - max).exp()))
+            .collect();
+        let sum: f32 = exp.iter().map(|(_, e)| e).sum();
+        exp.into_iter().map(|(lang, e)| (lang, e / sum)).collect()
+    }
+}
+
+/// Space-padded character trigrams of every Latin-script word in `text`.
+fn trigrams(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for word in text
+        .split(|c: char| Script::of(c) != Some(Script::Latin))
+        .filter(|w| !w.is_empty())
+    {
+        let padded: Vec<char> = std::iter::once(' ')
+            .chain(word.chars().flat_map(char::to_lowercase))
+            .chain(std::iter::once(' '))
+            .collect();
+        for window in padded.windows(3) {
+            out.push(window.iter().collect());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn top(text: &str) -> (Lang, Option<Script>) {
+        let detection = detect(text);
+        let top = detection.top().expect("a candidate");
+        (top.lang, detection.script)
+    }
+
+    #[test]
+    fn short_cjk_text() {
+        assert_eq!(top("你好"), (Lang::Zh, Some(Script::Han)));
+        assert_eq!(top("中文"), (Lang::Zh, Some(Script::Han)));
+        assert_eq!(top("안녕"), (Lang::Ko, Some(Script::Hangul)));
+    }
+
+    #[test]
+    fn kana_marks_japanese_even_among_kanji() {
+        assert_eq!(top("ありがとう"), (Lang::Ja, Some(Script::Kana)));
+        assert_eq!(top("カタカナ"), (Lang::Ja, Some(Script::Kana)));
+        // Mostly kanji, but the kana give it away.
+        assert_eq!(top("東京都に住む").0, Lang::Ja);
+        assert_eq!(top("日本語の文章です").0, Lang::Ja);
+    }
+
+    #[test]
+    fn mixed_scripts_rank_every_language() {
+        let detection = detect("Please translate this sentence, it mentions 中文 once.");
+        assert_eq!(detection.script, Some(Script::Latin));
+        let langs: Vec<Lang> = detection.candidates.iter().map(|c| c.lang).collect();
+        assert_eq!(langs[0], Lang::En);
+        assert!(langs.contains(&Lang::Zh));
+
+        let detection = detect("Привет, мир! Hello");
+        assert_eq!(detection.top().unwrap().lang, Lang::Ru);
+        assert!(detection.candidates.iter().any(|c| c.lang == Lang::En));
+
+        let confidences: f32 = detect("Hello 你好 こんにちは")
+            .candidates
+            .iter()
+            .map(|c| c.confidence)
+            .sum();
+        assert!((confidences - 1.0).abs() < 0.05, "{confidences}");
+    }
+
+    #[test]
+    fn latin_languages() {
+        let cases = [
+            ("The weather is nice today and we are going out", Lang::En),
+            ("Das Wetter ist heute schön und wir gehen raus", Lang::De),
+            ("Il fait beau aujourd'hui et nous allons sortir", Lang::Fr),
+            ("Hace buen tiempo hoy y vamos a salir", Lang::Es),
+        ];
+        for (text, lang) in cases {
+            assert_eq!(top(text), (lang, Some(Script::Latin)), "{text}");
+        }
+    }
+
+    #[test]
+    fn no_letters_means_no_candidates() {
+        let detection = detect("12:30 — 42!");
+        assert_eq!(detection.script, None);
+        assert!(detection.candidates.is_empty());
+    }
+}
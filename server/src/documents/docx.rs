@@ -0,0 +1,268 @@
+use std::io::{Cursor, Read, Write};
+
+use quick_xml::{
+    events::{BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{DocumentError, Translatable};
+
+/// Archives may unpack to this many times the upload limit; Word's own
+/// files stay well below.
+const MAX_UNPACK_RATIO: usize = 10;
+
+/// Word documents. Each `<w:p>` paragraph in the body, headers, footers,
+/// footnotes and endnotes is one unit. The translation goes into the
+/// paragraph's first `<w:t>` run and the other runs are emptied, so
+/// paragraph styles survive but formatting inside a paragraph collapses to
+/// that of its first run.
+pub(super) struct DocxDocument {
+    /// Every archive entry, in order, with its original bytes.
+    entries: Vec<Entry>,
+    units: Vec<String>,
+}
+
+struct Entry {
+    name: String,
+    data: Vec<u8>,
+    compression: CompressionMethod,
+    /// Set for XML parts that contain text.
+    part: Option<Part>,
+}
+
+struct Part {
+    events: Vec<Event<'static>>,
+    paragraphs: Vec<Paragraph>,
+}
+
+struct Paragraph {
+    unit: usize,
+    /// Indices of the `<w:t>` start events and of their text events.
+    starts: Vec<usize>,
+    texts: Vec<usize>,
+}
+
+impl DocxDocument {
+    /// `max_bytes` is the upload limit; entries are read only up to
+    /// `MAX_UNPACK_RATIO` times that in total, whatever sizes the archive
+    /// declares.
+    pub(super) fn parse(bytes: &[u8], max_bytes: usize) -> Result<Self, DocumentError> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(malformed)?;
+        let mut entries = Vec::with_capacity(archive.len());
+        let mut units = Vec::new();
+        let mut budget = max_bytes.saturating_mul(MAX_UNPACK_RATIO);
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(malformed)?;
+            let mut data = Vec::with_capacity((file.size() as usize).min(budget));
+            (&mut file)
+                .take(budget as u64 + 1)
+                .read_to_end(&mut data)
+                .map_err(malformed)?;
+            if data.len() > budget {
+                return Err(DocumentError::Malformed {
+                    format: "docx",
+                    message: format!(
+                        "unpacks to more than {} bytes",
+                        max_bytes.saturating_mul(MAX_UNPACK_RATIO)
+                    ),
+                });
+            }
+            budget -= data.len();
+            let name = file.name().to_string();
+            let part = if is_text_part(&name) {
+                Some(parse_part(&data, &mut units)?)
+            } else {
+                None
+            };
+            entries.push(Entry {
+                name,
+                data,
+                compression: file.compression(),
+                part,
+            });
+        }
+
+        if !entries.iter().any(|e| e.name == "word/document.xml") {
+            return Err(DocumentError::Malformed {
+                format: "docx",
+                message: "missing word/document.xml".into(),
+            });
+        }
+        Ok(Self { entries, units })
+    }
+}
+
+impl Translatable for DocxDocument {
+    fn units(&self) -> &[String] {
+        &self.units
+    }
+
+    fn render(&self, translations: &[String]) -> Result<Vec<u8>, DocumentError> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for entry in &self.entries {
+            let data = match &entry.part {
+                Some(part) => render_part(part, &self.units, translations)?,
+                None => entry.data.clone(),
+            };
+            let options = FileOptions::default().compression_method(entry.compression);
+            zip.start_file(entry.name.as_str(), options)
+                .map_err(malformed)?;
+            zip.write_all(&data).map_err(malformed)?;
+        }
+        Ok(zip.finish().map_err(malformed)?.into_inner())
+    }
+}
+
+fn is_text_part(name: &str) -> bool {
+    let Some(file) = name.strip_prefix("word/") else {
+        return false;
+    };
+    file == "document.xml"
+        || file == "footnotes.xml"
+        || file == "endnotes.xml"
+        || ((file.starts_with("header") || file.starts_with("footer"))
+            && file.ends_with(".xml")
+            && !file.contains('/'))
+}
+
+fn parse_part(data: &[u8], units: &mut Vec<String>) -> Result<Part, DocumentError> {
+    let mut reader = Reader::from_reader(data);
+    let mut buf = Vec::new();
+    let mut events = Vec::new();
+    let mut paragraphs = Vec::new();
+    // Paragraphs nest (text boxes); text belongs to the innermost one.
+    let mut open: Vec<(Paragraph, String)> = Vec::new();
+    let mut in_text = false;
+
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(malformed)?;
+        match &event {
+            Event::Eof => break,
+            Event::Start(e) if e.name().as_ref() == b"w:p" => open.push((
+                Paragraph {
+                    unit: 0,
+                    starts: Vec::new(),
+                    texts: Vec::new(),
+                },
+                String::new(),
+            )),
+            Event::End(e) if e.name().as_ref() == b"w:p" => {
+                if let Some((mut paragraph, text)) = open.pop() {
+                    if text.chars().any(char::is_alphabetic) {
+                        paragraph.unit = units.len();
+                        units.push(text);
+                        paragraphs.push(paragraph);
+                    }
+                }
+            }
+            Event::Start(e) if e.name().as_ref() == b"w:t" => {
+                in_text = true;
+                if let Some((paragraph, _)) = open.last_mut() {
+                    paragraph.starts.push(events.len());
+                }
+            }
+            Event::End(e) if e.name().as_ref() == b"w:t" => in_text = false,
+            Event::Text(e) if in_text => {
+                if let Some((paragraph, text)) = open.last_mut() {
+                    paragraph.texts.push(events.len());
+                    text.push_str(&e.unescape().map_err(malformed)?);
+                }
+            }
+            _ => {}
+        }
+        events.push(event.into_owned());
+        buf.clear();
+    }
+
+    Ok(Part { events, paragraphs })
+}
+
+fn render_part(
+    part: &Part,
+    units: &[String],
+    translations: &[String],
+) -> Result<Vec<u8>, DocumentError> {
+    let mut events = part.events.clone();
+    for paragraph in &part.paragraphs {
+        let translation = translations
+            .get(paragraph.unit)
+            .unwrap_or(&units[paragraph.unit]);
+        for (n, &index) in paragraph.texts.iter().enumerate() {
+            let text = if n == 0 { translation.as_str() } else { "" };
+            events[index] = Event::Text(BytesText::new(text).into_owned());
+        }
+        // Leading and trailing spaces in a run are dropped unless preserved.
+        for &index in &paragraph.starts {
+            if let Event::Start(start) = &events[index] {
+                events[index] = Event::Start(preserve_space(start));
+            }
+        }
+    }
+
+    let mut writer = Writer::new(Vec::new());
+    for event in events {
+        writer.write_event(event).map_err(malformed)?;
+    }
+    Ok(writer.into_inner())
+}
+
+fn preserve_space(start: &BytesStart) -> BytesStart<'static> {
+    let mut start = start.to_owned();
+    if start
+        .try_get_attribute("xml:space")
+        .ok()
+        .flatten()
+        .is_none()
+    {
+        start.push_attribute(("xml:space", "preserve"));
+    }
+    start
+}
+
+fn malformed(err: impl std::fmt::Display) -> DocumentError {
+    DocumentError::Malformed {
+        format: "docx",
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str =
+        r#"<w:document><w:body><w:p><w:r><w:t>Hello</w:t></w:r></w:p></w:body></w:document>"#;
+
+    fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn parses_paragraphs() {
+        let bytes = archive(&[("word/document.xml", DOCUMENT.as_bytes())]);
+        let doc = DocxDocument::parse(&bytes, bytes.len()).unwrap();
+        assert_eq!(doc.units(), ["Hello"]);
+    }
+
+    #[test]
+    fn rejects_archives_unpacking_past_the_limit() {
+        let padding = vec![0u8; 1024 * 1024];
+        let bytes = archive(&[
+            ("word/document.xml", DOCUMENT.as_bytes()),
+            ("word/media/padding.bin", &padding),
+        ]);
+        assert!(bytes.len() * MAX_UNPACK_RATIO < padding.len());
+        let err = DocxDocument::parse(&bytes, bytes.len())
+            .err()
+            .expect("over the limit");
+        assert!(err.to_string().contains("unpacks to more than"), "{err}");
+    }
+}
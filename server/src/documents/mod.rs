@@ -0,0 +1,396 @@
+//! Uploaded documents: split a file into translatable units and rebuild it,
+//! in the same format, from their translations.
+
+use crate::markdown;
+
+mod docx;
+mod po;
+mod subtitles;
+mod txt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DocFormat {
+    Txt,
+    Markdown,
+    Docx,
+    Srt,
+    Vtt,
+    Po,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DocumentError {
+    #[error("unsupported file type: {0}")]
+    Unsupported(String),
+    #[error("file is not valid UTF-8")]
+    Encoding,
+    #[error("malformed {format} file: {message}")]
+    Malformed {
+        format: &'static str,
+        message: String,
+    },
+}
+
+impl DocFormat {
+    /// Picks the format from the file extension.
+    pub fn from_filename(name: &str) -> Result<Self, DocumentError> {
+        let ext = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "txt" => Ok(DocFormat::Txt),
+            "md" | "markdown" => Ok(DocFormat::Markdown),
+            "docx" => Ok(DocFormat::Docx),
+            "srt" => Ok(DocFormat::Srt),
+            "vtt" => Ok(DocFormat::Vtt),
+            "po" => Ok(DocFormat::Po),
+            _ => Err(DocumentError::Unsupported(name.to_string())),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DocFormat::Txt => "txt",
+            DocFormat::Markdown => "md",
+            DocFormat::Docx => "docx",
+            DocFormat::Srt => "srt",
+            DocFormat::Vtt => "vtt",
+            DocFormat::Po => "po",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            DocFormat::Txt => "text/plain; charset=utf-8",
+            DocFormat::Markdown => "text/markdown; charset=utf-8",
+            DocFormat::Docx => {
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+            }
+            DocFormat::Srt => "application/x-subrip; charset=utf-8",
+            DocFormat::Vtt => "text/vtt; charset=utf-8",
+            DocFormat::Po => "text/x-gettext-translation; charset=utf-8",
+        }
+    }
+}
+
+/// A parsed document.
+pub trait Translatable: Send + Sync {
+    /// Texts to translate, in document order.
+    fn units(&self) -> &[String];
+
+    /// Rebuilds the file with one translation per unit.
+    fn render(&self, translations: &[String]) -> Result<Vec<u8>, DocumentError>;
+}
+
+/// `max_bytes` is the upload limit, which also bounds how far archives
+/// (`.docx`) may unpack.
+pub fn parse(
+    format: DocFormat,
+    bytes: &[u8],
+    max_bytes: usize,
+) -> Result<Box<dyn Translatable>, DocumentError> {
+    if format == DocFormat::Docx {
+        return Ok(Box::new(docx::DocxDocument::parse(bytes, max_bytes)?));
+    }
+    let text = TextFile::decode(bytes)?;
+    let template = match format {
+        DocFormat::Txt => txt::parse(&text.body),
+        DocFormat::Srt | DocFormat::Vtt => subtitles::parse(&text.body),
+        DocFormat::Po => po::parse(&text.body)?,
+        DocFormat::Markdown => return Ok(Box::new(MarkdownDocument::new(text))),
+        DocFormat::Docx => unreachable!("handled above"),
+    };
+    Ok(Box::new(TemplateDocument {
+        file: text,
+        template,
+    }))
+}
+
+/// A UTF-8 text file, normalised to `\n` line endings. The byte-order mark
+/// and `\r\n` endings are put back on output.
+struct TextFile {
+    body: String,
+    bom: bool,
+    crlf: bool,
+}
+
+impl TextFile {
+    fn decode(bytes: &[u8]) -> Result<Self, DocumentError> {
+        let text = std::str::from_utf8(bytes).map_err(|_| DocumentError::Encoding)?;
+        let (bom, text) = match text.strip_prefix('\u{feff}') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let crlf = text.contains("\r\n");
+        Ok(Self {
+            body: text.replace("\r\n", "\n"),
+            bom,
+            crlf,
+        })
+    }
+
+    fn encode(&self, body: &str) -> Vec<u8> {
+        let mut out = String::with_capacity(body.len() + 3);
+        if self.bom {
+            out.push('\u{feff}');
+        }
+        if self.crlf {
+            out.push_str(&body.replace('\n', "\r\n"));
+        } else {
+            out.push_str(body);
+        }
+        out.into_bytes()
+    }
+}
+
+/// Literal text interleaved with translatable units.
+struct Template {
+    parts: Vec<Part>,
+    units: Vec<String>,
+    /// Applied to each translation before it is written out.
+    escape: fn(&str) -> String,
+}
+
+enum Part {
+    Literal(String),
+    Unit {
+        index: usize,
+        prefix: String,
+        suffix: String,
+    },
+}
+
+impl Template {
+    fn new(escape: fn(&str) -> String) -> Self {
+        Self {
+            parts: Vec::new(),
+            units: Vec::new(),
+            escape,
+        }
+    }
+
+    fn literal(&mut self, text: &str) {
+        match self.parts.last_mut() {
+            Some(Part::Literal(last)) => last.push_str(text),
+            _ => self.parts.push(Part::Literal(text.to_string())),
+        }
+    }
+
+    /// Adds a unit written as `prefix + translation + suffix`. Text without
+    /// letters (numbers, punctuation) is kept as is.
+    fn unit(&mut self, text: &str, prefix: &str, suffix: &str) {
+        if !text.chars().any(char::is_alphabetic) {
+            self.literal(prefix);
+            self.literal(&(self.escape)(text));
+            self.literal(suffix);
+            return;
+        }
+        self.parts.push(Part::Unit {
+            index: self.units.len(),
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+        });
+        self.units.push(text.to_string());
+    }
+
+    fn render(&self, translations: &[String]) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Unit {
+                    index,
+                    prefix,
+                    suffix,
+                } => {
+                    let translation = translations
+                        .get(*index)
+                        .map_or(self.units[*index].as_str(), String::as_str);
+                    out.push_str(prefix);
+                    out.push_str(&(self.escape)(translation));
+                    out.push_str(suffix);
+                }
+            }
+        }
+        out
+    }
+}
+
+struct TemplateDocument {
+    file: TextFile,
+    template: Template,
+}
+
+impl Translatable for TemplateDocument {
+    fn units(&self) -> &[String] {
+        &self.template.units
+    }
+
+    fn render(&self, translations: &[String]) -> Result<Vec<u8>, DocumentError> {
+        Ok(self.file.encode(&self.template.render(translations)))
+    }
+}
+
+/// Markdown goes through the same segmenter as `format: "markdown"`.
+struct MarkdownDocument {
+    file: TextFile,
+    units: Vec<String>,
+}
+
+impl MarkdownDocument {
+    fn new(file: TextFile) -> Self {
+        let units = markdown::Document::parse(&file.body)
+            .segments()
+            .map(str::to_string)
+            .collect();
+        Self { file, units }
+    }
+}
+
+impl Translatable for MarkdownDocument {
+    fn units(&self) -> &[String] {
+        &self.units
+    }
+
+    fn render(&self, translations: &[String]) -> Result<Vec<u8>, DocumentError> {
+        let rendered = markdown::Document::parse(&self.file.body).render(translations);
+        Ok(self.file.encode(&rendered))
+    }
+}
+
+/// Splits a unit longer than `max` characters into pieces at line and
+/// sentence boundaries. Each piece comes with the whitespace that followed
+/// it, to be put back between translated pieces.
+pub fn chunks(text: &str, max: usize) -> Vec<(&str, &str)> {
+    if text.chars().count() <= max {
+        return vec![(text, "")];
+    }
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    // Byte offset just after the last place a piece may end.
+    let mut last_break: Option<usize> = None;
+    let mut chars = 0;
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if chars >= max {
+            let end = last_break.filter(|&b| b > start).unwrap_or(i);
+            pieces.push(&text[start..end]);
+            start = end;
+            last_break = None;
+            chars = text[start..i].chars().count();
+        }
+        if matches!(prev, Some('。' | '！' | '？')) {
+            last_break = Some(i);
+        }
+        if c == '\n' || (c.is_whitespace() && matches!(prev, Some('.' | '!' | '?' | ';'))) {
+            last_break = Some(i + c.len_utf8());
+        }
+        chars += 1;
+        prev = Some(c);
+    }
+    pieces.push(&text[start..]);
+
+    pieces
+        .into_iter()
+        .map(|piece| {
+            let core = piece.trim_end();
+            (core, &piece[core.len()..])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `raw`, checks its units and renders them upper-cased.
+    fn round_trip(format: DocFormat, raw: &str, units: &[&str]) -> String {
+        let doc = parse(format, raw.as_bytes(), raw.len()).unwrap();
+        assert_eq!(doc.units(), units);
+        let translations: Vec<String> = doc.units().iter().map(|u| u.to_uppercase()).collect();
+        String::from_utf8(doc.render(&translations).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn txt_paragraphs_keep_layout() {
+        let raw = "\u{feff}  First line\r\nstill first.\r\n\r\n42\r\nSecond\r\n";
+        let out = round_trip(
+            DocFormat::Txt,
+            raw,
+            &["First line\nstill first.", "42\nSecond"],
+        );
+        assert_eq!(
+            out,
+            "\u{feff}  FIRST LINE\r\nSTILL FIRST.\r\n\r\n42\r\nSECOND\r\n"
+        );
+    }
+
+    #[test]
+    fn subtitle_cues() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nHello\nthere\n\n2\n00:00:03,000 --> 00:00:04,000\n...\n";
+        let out = round_trip(DocFormat::Srt, srt, &["Hello\nthere"]);
+        assert_eq!(out, srt.replace("Hello\nthere", "HELLO\nTHERE"));
+
+        let vtt = "WEBVTT\n\nNOTE keep me\n\nintro\n00:01.000 --> 00:02.000 align:start\nHi\n";
+        let out = round_trip(DocFormat::Vtt, vtt, &["Hi"]);
+        assert_eq!(out, vtt.replace("\nHi\n", "\nHI\n"));
+
+        // A translation must not end the cue with a blank line.
+        let doc = parse(DocFormat::Srt, srt.as_bytes(), srt.len()).unwrap();
+        let out = String::from_utf8(doc.render(&["a\n\nb".to_string()]).unwrap()).unwrap();
+        assert!(out.contains("02,000\na\nb\n\n2\n"), "{out}");
+    }
+
+    #[test]
+    fn po_translates_only_empty_msgstrs() {
+        let raw = r#"msgid ""
+msgstr "Content-Type: text/plain; charset=UTF-8\n"
+
+#: src/main.c:1
+msgid "Save \"file\""
+msgstr ""
+
+msgid "Done"
+msgstr "Fertig"
+
+msgid "One file"
+msgid_plural "Many files"
+msgstr[0] ""
+msgstr[1] ""
+"#;
+        let out = round_trip(
+            DocFormat::Po,
+            raw,
+            &["Save \"file\"", "One file", "Many files"],
+        );
+        assert!(out.contains("msgstr \"SAVE \\\"FILE\\\"\"\n"), "{out}");
+        assert!(out.contains("msgstr \"Fertig\""));
+        assert!(out.contains("msgstr[0] \"ONE FILE\"\nmsgstr[1] \"MANY FILES\"\n"));
+
+        let err = parse(DocFormat::Po, b"msgid \"a\"\nmsgstr oops\n", 100).err();
+        assert!(err.unwrap().to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn chunks_split_at_sentence_and_line_boundaries() {
+        assert_eq!(chunks("short", 10), [("short", "")]);
+
+        let text = "One two. Three four. Five six.";
+        let pieces = chunks(text, 12);
+        assert_eq!(
+            pieces,
+            [("One two.", " "), ("Three four.", " "), ("Five six.", "")]
+        );
+
+        let cjk = "第一句。第二句。第三句。";
+        let pieces: Vec<&str> = chunks(cjk, 5).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(pieces, ["第一句。", "第二句。", "第三句。"]);
+
+        // Without a boundary, pieces are cut at the limit.
+        let long = "a".repeat(25);
+        let sizes: Vec<usize> = chunks(&long, 10).iter().map(|(p, _)| p.len()).collect();
+        assert_eq!(sizes, [10, 10, 5]);
+    }
+}
@@ -0,0 +1,143 @@
+use super::{DocumentError, Template};
+
+/// Gettext catalogs: every entry with an empty `msgstr` gets its `msgid`
+/// (or `msgid_plural` for `msgstr[1..]`) translated. Entries that already
+/// have a translation, the header entry and obsolete `#~` entries are kept.
+pub(super) fn parse(body: &str) -> Result<Template, DocumentError> {
+    let mut template = Template::new(escape);
+    let mut block: Vec<&str> = Vec::new();
+    let mut first_line = 1;
+
+    for (number, line) in body.split_inclusive('\n').enumerate() {
+        if line.trim().is_empty() {
+            flush(&mut template, &mut block, first_line)?;
+            template.literal(line);
+            first_line = number + 2;
+        } else {
+            block.push(line);
+        }
+    }
+    flush(&mut template, &mut block, first_line)?;
+    Ok(template)
+}
+
+/// `msgid`, `msgstr[1]`, ... with its unescaped value and the block lines
+/// it spans.
+struct Field {
+    keyword: String,
+    lines: std::ops::Range<usize>,
+    value: String,
+}
+
+fn flush(
+    template: &mut Template,
+    block: &mut Vec<&str>,
+    first_line: usize,
+) -> Result<(), DocumentError> {
+    if block.is_empty() {
+        return Ok(());
+    }
+    let fields = parse_fields(block, first_line)?;
+    let value = |keyword: &str| {
+        fields
+            .iter()
+            .find(|f| f.keyword == keyword)
+            .map(|f| f.value.as_str())
+    };
+    let msgstrs: Vec<&Field> = fields
+        .iter()
+        .filter(|f| f.keyword.starts_with("msgstr"))
+        .collect();
+
+    let msgid = value("msgid").unwrap_or_default();
+    let untranslated = !msgstrs.is_empty() && msgstrs.iter().all(|f| f.value.is_empty());
+    if msgid.is_empty() || !untranslated {
+        template.literal(&block.concat());
+        block.clear();
+        return Ok(());
+    }
+
+    let plural = value("msgid_plural").unwrap_or(msgid);
+    let mut at = 0;
+    for field in msgstrs {
+        template.literal(&block[at..field.lines.start].concat());
+        let source = match field.keyword.as_str() {
+            "msgstr" | "msgstr[0]" => msgid,
+            _ => plural,
+        };
+        template.unit(source, &format!("{} \"", field.keyword), "\"\n");
+        at = field.lines.end;
+    }
+    template.literal(&block[at..].concat());
+    block.clear();
+    Ok(())
+}
+
+fn parse_fields(block: &[&str], first_line: usize) -> Result<Vec<Field>, DocumentError> {
+    let mut fields: Vec<Field> = Vec::new();
+    for (i, line) in block.iter().enumerate() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let malformed = |message: &str| DocumentError::Malformed {
+            format: "po",
+            message: format!("line {}: {}", first_line + i, message),
+        };
+        if line.starts_with('"') {
+            let field = fields
+                .last_mut()
+                .ok_or_else(|| malformed("string without keyword"))?;
+            field
+                .value
+                .push_str(&unquote(line).ok_or_else(|| malformed("bad string"))?);
+            field.lines.end = i + 1;
+            continue;
+        }
+        let (keyword, rest) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| malformed("expected `keyword \"string\"`"))?;
+        let value = unquote(rest.trim()).ok_or_else(|| malformed("bad string"))?;
+        fields.push(Field {
+            keyword: keyword.to_string(),
+            lines: i..i + 1,
+            value,
+        });
+    }
+    Ok(fields)
+}
+
+/// `"a\"b\n"` → `a"b` + newline.
+fn unquote(raw: &str) -> Option<String> {
+    let inner = raw.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            'r' => out.push('\r'),
+            other => out.push(other),
+        }
+    }
+    Some(out)
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,47 @@
+use super::Template;
+
+/// SubRip and WebVTT. Cues are blocks separated by blank lines; everything
+/// up to and including the `-->` timing line (index, cue id, settings) is
+/// kept, and the text lines after it are one unit. Blocks without a timing
+/// line (`WEBVTT`, `NOTE`, `STYLE`) pass through.
+pub(super) fn parse(body: &str) -> Template {
+    let mut template = Template::new(single_cue);
+    let mut block: Vec<&str> = Vec::new();
+
+    for line in body.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            flush(&mut template, &mut block);
+            template.literal(line);
+        } else {
+            block.push(line);
+        }
+    }
+    flush(&mut template, &mut block);
+    template
+}
+
+fn flush(template: &mut Template, block: &mut Vec<&str>) {
+    if block.is_empty() {
+        return;
+    }
+    match block.iter().position(|line| line.contains("-->")) {
+        Some(timing) if timing + 1 < block.len() => {
+            template.literal(&block[..=timing].concat());
+            let text = block[timing + 1..].concat();
+            let trailing = &text[text.trim_end().len()..];
+            template.unit(text.trim_end(), "", trailing);
+        }
+        _ => template.literal(&block.concat()),
+    }
+    block.clear();
+}
+
+/// A blank line would end the cue early, so translations keep their line
+/// breaks but never an empty line.
+fn single_cue(text: &str) -> String {
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
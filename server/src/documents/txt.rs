@@ -0,0 +1,30 @@
+use super::Template;
+
+/// Plain text: each paragraph (lines up to a blank line) is one unit; blank
+/// lines and the indentation of each paragraph are kept.
+pub(super) fn parse(body: &str) -> Template {
+    let mut template = Template::new(str::to_string);
+    let mut paragraph: Vec<&str> = Vec::new();
+
+    for line in body.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            flush(&mut template, &mut paragraph);
+            template.literal(line);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush(&mut template, &mut paragraph);
+    template
+}
+
+fn flush(template: &mut Template, paragraph: &mut Vec<&str>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    let raw = paragraph.concat();
+    let indent = &raw[..raw.len() - raw.trim_start().len()];
+    let trailing = &raw[raw.trim_end().len()..];
+    template.unit(raw.trim(), indent, trailing);
+    paragraph.clear();
+}
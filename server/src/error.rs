@@ -16,18 +16,52 @@ pub struct ApiErrorBody {
 pub enum ApiError {
     #[error("BadRequest: {0}")]
     BadRequest(String),
+    /// A `BadRequest` with machine-readable `details`, e.g. where a JSON
+    /// document stops parsing.
+    #[error("BadRequest: {message}")]
+    BadRequestDetails {
+        message: String,
+        details: serde_json::Value,
+    },
     #[error("Internal: {0}")]
     Internal(String),
     #[error("NotFound: {0}")]
     NotFound(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    /// A daily token budget is used up.
+    #[error("QuotaExceeded: {0}")]
+    QuotaExceeded(String),
+    /// The server is at capacity for this kind of work; retry later.
+    #[error("Busy: {0}")]
+    Busy(String),
+    /// Translation is disabled because no provider could be configured.
+    #[error("ProviderNotConfigured: {0}")]
+    ProviderNotConfigured(String),
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, code, message) = match self {
+            ApiError::BadRequestDetails { message, details } => {
+                let body = ApiErrorBody {
+                    code: "BadRequest",
+                    message,
+                    details: Some(details),
+                };
+                return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+            }
             ApiError::BadRequest(m) => (StatusCode::BAD_REQUEST, "BadRequest", m),
             ApiError::Internal(m) => (StatusCode::INTERNAL_SERVER_ERROR, "Internal", m),
             ApiError::NotFound(m) => (StatusCode::NOT_FOUND, "NotFound", m),
+            ApiError::Unauthorized(m) => (StatusCode::UNAUTHORIZED, "Unauthorized", m),
+            ApiError::QuotaExceeded(m) => (StatusCode::TOO_MANY_REQUESTS, "QuotaExceeded", m),
+            ApiError::Busy(m) => (StatusCode::SERVICE_UNAVAILABLE, "Busy", m),
+            ApiError::ProviderNotConfigured(m) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "ProviderNotConfigured",
+                format!("translation is disabled: {}", m),
+            ),
         };
 
         let body = ApiErrorBody {
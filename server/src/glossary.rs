@@ -0,0 +1,194 @@
+//! Named glossaries: required source → target term mappings plus
+//! do-not-translate terms.
+//!
+//! Terms are swapped for placeholders before the text reaches a provider,
+//! so the model cannot paraphrase them, and swapped back afterwards. A
+//! placeholder the model dropped is reported as a violation.
+
+use std::{collections::HashMap, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::providers::Lang;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GlossaryFile {
+    #[serde(default)]
+    source_lang: Option<String>,
+    #[serde(default)]
+    target_lang: Option<String>,
+    #[serde(default)]
+    terms: Vec<TermFile>,
+    #[serde(default)]
+    do_not_translate: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TermFile {
+    source: String,
+    target: String,
+}
+
+pub struct Glossary {
+    /// Restricts the glossary to one language pair when set.
+    pub source_lang: Option<Lang>,
+    pub target_lang: Option<Lang>,
+    /// `(source term, replacement, kind)`, longest source first so that
+    /// "Magic Box Pro" wins over "Magic Box".
+    entries: Vec<(String, String, TermKind)>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TermKind {
+    Term,
+    DoNotTranslate,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    pub term: String,
+    pub expected: String,
+    pub kind: TermKind,
+}
+
+/// Text with glossary terms replaced by placeholders.
+pub struct Masked {
+    pub text: String,
+    slots: Vec<Slot>,
+}
+
+struct Slot {
+    placeholder: String,
+    term: String,
+    replacement: String,
+    kind: TermKind,
+}
+
+#[derive(Default)]
+pub struct GlossaryStore {
+    glossaries: HashMap<String, Glossary>,
+}
+
+impl GlossaryStore {
+    /// Parses `{ "<name>": { "terms": [...], "doNotTranslate": [...] } }`.
+    pub fn from_json(raw: &str) -> anyhow::Result<Self> {
+        let files: HashMap<String, GlossaryFile> = serde_json::from_str(raw)?;
+        let mut glossaries = HashMap::new();
+        for (name, file) in files {
+            let lang = |value: Option<String>| -> anyhow::Result<Option<Lang>> {
+                value
+                    .map(|v| {
+                        Lang::from_str(&v).map_err(|_| {
+                            anyhow::anyhow!("glossary {}: unsupported language {}", name, v)
+                        })
+                    })
+                    .transpose()
+            };
+            let mut entries: Vec<(String, String, TermKind)> = file
+                .terms
+                .into_iter()
+                .map(|t| (t.source, t.target, TermKind::Term))
+                .chain(
+                    file.do_not_translate
+                        .into_iter()
+                        .map(|t| (t.clone(), t, TermKind::DoNotTranslate)),
+                )
+                .filter(|(source, _, _)| !source.trim().is_empty())
+                .collect();
+            entries.sort_by_key(|e| std::cmp::Reverse(e.0.len()));
+            let glossary = Glossary {
+                source_lang: lang(file.source_lang)?,
+                target_lang: lang(file.target_lang)?,
+                entries,
+            };
+            glossaries.insert(name, glossary);
+        }
+        Ok(Self { glossaries })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Glossary> {
+        self.glossaries.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = (&str, &Glossary)> {
+        self.glossaries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+impl Glossary {
+    pub fn term_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn mask(&self, text: &str) -> Masked {
+        let mut out = String::with_capacity(text.len());
+        let mut slots: Vec<Slot> = Vec::new();
+        let mut rest = text;
+        let mut prev: Option<char> = None;
+
+        'scan: while let Some(c) = rest.chars().next() {
+            for (term, replacement, kind) in &self.entries {
+                if rest.starts_with(term.as_str()) && on_boundaries(prev, term, &rest[term.len()..])
+                {
+                    let index = match slots.iter().position(|s| &s.term == term) {
+                        Some(index) => index,
+                        None => {
+                            slots.push(Slot {
+                                placeholder: format!("{{{{G{}}}}}", slots.len() + 1),
+                                term: term.clone(),
+                                replacement: replacement.clone(),
+                                kind: *kind,
+                            });
+                            slots.len() - 1
+                        }
+                    };
+                    out.push_str(&slots[index].placeholder);
+                    prev = term.chars().last();
+                    rest = &rest[term.len()..];
+                    continue 'scan;
+                }
+            }
+            out.push(c);
+            prev = Some(c);
+            rest = &rest[c.len_utf8()..];
+        }
+
+        Masked { text: out, slots }
+    }
+}
+
+impl Masked {
+    /// Puts the required terms back and reports placeholders the provider
+    /// failed to keep.
+    pub fn restore(&self, output: &str) -> (String, Vec<Violation>) {
+        let mut restored = output.to_string();
+        let mut violations = Vec::new();
+        for slot in &self.slots {
+            if restored.contains(&slot.placeholder) {
+                restored = restored.replace(&slot.placeholder, &slot.replacement);
+            } else {
+                violations.push(Violation {
+                    term: slot.term.clone(),
+                    expected: slot.replacement.clone(),
+                    kind: slot.kind,
+                });
+            }
+        }
+        (restored, violations)
+    }
+}
+
+/// Alphanumeric edges of a term must not run into neighbouring letters, so
+/// "API" does not match inside "RAPID". Edges in scripts without spaces
+/// (CJK) match anywhere.
+fn on_boundaries(prev: Option<char>, term: &str, after: &str) -> bool {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let starts_word = term.chars().next().is_some_and(is_word);
+    let ends_word = term.chars().last().is_some_and(is_word);
+    let before_ok = !starts_word || !prev.is_some_and(is_word);
+    let after_ok = !ends_word || !after.chars().next().is_some_and(is_word);
+    before_ok && after_ok
+}
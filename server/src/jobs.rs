@@ -0,0 +1,145 @@
+//! In-memory registry of background document-translation jobs.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use crate::{documents::DocFormat, providers::Lang};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Clone)]
+pub struct Job {
+    pub id: String,
+    pub filename: String,
+    pub format: DocFormat,
+    pub source: Lang,
+    pub target: Lang,
+    pub status: JobStatus,
+    /// Distinct texts translated so far, out of `total`.
+    pub done: usize,
+    pub total: usize,
+    pub error: Option<String>,
+    pub result: Option<Arc<Vec<u8>>>,
+    finished_at: Option<Instant>,
+}
+
+pub struct JobStore {
+    jobs: Mutex<HashMap<String, Job>>,
+    /// Finished jobs (and their results) are dropped after this long.
+    ttl: Duration,
+    /// Bounds how many jobs translate at once; the rest stay `queued`.
+    pub slots: Arc<Semaphore>,
+    pub max_bytes: usize,
+    /// Queued plus running jobs accepted at once; each holds its parsed
+    /// document in memory.
+    max_pending: usize,
+}
+
+/// `create` refused a job because `max_pending` are already in flight.
+#[derive(Debug)]
+pub struct TooManyJobs(pub usize);
+
+impl JobStore {
+    pub fn new(ttl: Duration, max_running: usize, max_pending: usize, max_bytes: usize) -> Self {
+        let max_running = max_running.max(1);
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            ttl,
+            slots: Arc::new(Semaphore::new(max_running)),
+            max_bytes,
+            max_pending: max_pending.max(max_running),
+        }
+    }
+
+    /// Registers a queued job, unless `max_pending` jobs are still queued or
+    /// running.
+    pub fn create(
+        &self,
+        filename: String,
+        format: DocFormat,
+        source: Lang,
+        target: Lang,
+    ) -> Result<Job, TooManyJobs> {
+        let job = Job {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            filename,
+            format,
+            source,
+            target,
+            status: JobStatus::Queued,
+            done: 0,
+            total: 0,
+            error: None,
+            result: None,
+            finished_at: None,
+        };
+        let mut jobs = self.jobs.lock().expect("job store poisoned");
+        self.purge(&mut jobs);
+        let pending = jobs
+            .values()
+            .filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Running))
+            .count();
+        if pending >= self.max_pending {
+            return Err(TooManyJobs(self.max_pending));
+        }
+        jobs.insert(job.id.clone(), job.clone());
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.jobs.lock().expect("job store poisoned");
+        self.purge(&mut jobs);
+        jobs.get(id).cloned()
+    }
+
+    pub fn update(&self, id: &str, f: impl FnOnce(&mut Job)) {
+        let mut jobs = self.jobs.lock().expect("job store poisoned");
+        if let Some(job) = jobs.get_mut(id) {
+            f(job);
+            if matches!(job.status, JobStatus::Done | JobStatus::Failed) {
+                job.finished_at.get_or_insert_with(Instant::now);
+            }
+        }
+    }
+
+    fn purge(&self, jobs: &mut HashMap<String, Job>) {
+        let ttl = self.ttl;
+        jobs.retain(|_, job| job.finished_at.is_none_or(|at| at.elapsed() < ttl));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(store: &JobStore) -> Result<Job, TooManyJobs> {
+        store.create("a.txt".into(), DocFormat::Txt, Lang::En, Lang::De)
+    }
+
+    #[test]
+    fn pending_jobs_are_capped() {
+        let store = JobStore::new(Duration::from_secs(60), 1, 2, 1024);
+        let first = create(&store).unwrap();
+        let second = create(&store).unwrap();
+        store.update(&second.id, |job| job.status = JobStatus::Running);
+        assert!(matches!(create(&store), Err(TooManyJobs(2))));
+
+        // Finished jobs no longer count, though their results are kept.
+        store.update(&first.id, |job| job.status = JobStatus::Done);
+        create(&store).unwrap();
+        assert_eq!(store.get(&first.id).unwrap().status, JobStatus::Done);
+    }
+}
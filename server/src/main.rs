@@ -1,13 +1,26 @@
 use std::{net::SocketAddr, time::Duration};
 
-use tracing::info;
+use tracing::{info, warn};
 
 mod app;
+mod cache;
+mod detect;
+mod documents;
 mod error;
+mod glossary;
+mod jobs;
+mod markdown;
+mod memory;
 mod providers;
 mod routes;
+#[cfg(test)]
+mod test_support;
+mod usage;
 
-use app::{build_cache, build_provider, AppState};
+use app::{
+    build_admin_token, build_cache, build_glossaries, build_jobs, build_memory, build_providers,
+    build_usage, AppState, ProviderSetupError,
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -20,9 +33,32 @@ async fn main() -> anyhow::Result<()> {
         .compact()
         .init();
 
-    let provider = build_provider()?;
-    let cache = build_cache();
-    let state = AppState::new(provider, cache);
+    // Without a provider the server still starts; only translation is off.
+    // A provider that is configured but wrongly still stops start-up.
+    let providers = match build_providers() {
+        Ok(chain) => Ok(chain),
+        Err(ProviderSetupError::NotConfigured(reason)) => {
+            warn!(reason = %reason, "no translate provider configured, translation disabled");
+            Err(reason)
+        }
+        Err(ProviderSetupError::Invalid(err)) => {
+            return Err(err.context("translate provider configuration"))
+        }
+    };
+    let cache = build_cache()?;
+    info!(backend = cache.name(), "translation cache ready");
+    let glossaries = build_glossaries()?;
+    let jobs = build_jobs();
+    let memory = build_memory()?;
+    let state = AppState::new(
+        providers,
+        cache,
+        glossaries,
+        jobs,
+        memory,
+        build_usage(),
+        build_admin_token(),
+    );
     let app = routes::router(state);
 
     let port: u16 = std::env::var("PORT")
@@ -0,0 +1,476 @@
+//! Markdown-aware translation: split a document into prose segments, then
+//! splice translated segments back into the original source.
+//!
+//! Everything outside a segment is copied byte for byte, so block structure
+//! (headings, lists, quotes, tables), code blocks, HTML and front matter
+//! cannot change. Inside a segment, inline code, URLs, link targets and
+//! inline HTML are swapped for `{{M1}}` placeholders before translation and
+//! restored afterwards.
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag};
+
+pub struct Document<'a> {
+    src: &'a str,
+    segments: Vec<Segment>,
+}
+
+struct Segment {
+    range: Range<usize>,
+    /// Source of the segment with protected spans replaced by placeholders.
+    text: String,
+    /// `(placeholder, original source)`.
+    protected: Vec<(String, String)>,
+    in_table: bool,
+}
+
+/// A run of inline content being collected into a segment.
+struct Run {
+    range: Range<usize>,
+    protected: Vec<Range<usize>>,
+    in_table: bool,
+}
+
+impl<'a> Document<'a> {
+    pub fn parse(src: &'a str) -> Self {
+        let body = front_matter_end(src);
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        options.insert(Options::ENABLE_FOOTNOTES);
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_TASKLISTS);
+
+        let mut collector = Collector {
+            src,
+            run: None,
+            segments: Vec::new(),
+            skip: 0,
+            table: 0,
+        };
+        for (event, range) in Parser::new_ext(&src[body..], options).into_offset_iter() {
+            let range = range.start + body..range.end + body;
+            collector.event(event, range);
+        }
+        collector.flush();
+
+        Self {
+            src,
+            segments: collector.segments,
+        }
+    }
+
+    /// Texts to translate, in document order.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().map(|s| s.text.as_str())
+    }
+
+    /// Rebuilds the document with one translation per segment. A translation
+    /// that lost a placeholder is discarded and the original segment kept,
+    /// so code and link targets are never dropped.
+    pub fn render(&self, translations: &[String]) -> String {
+        let mut out = String::with_capacity(self.src.len());
+        let mut at = 0;
+        for (segment, translation) in self.segments.iter().zip(translations) {
+            out.push_str(&self.src[at..segment.range.start]);
+            match segment.restore(translation) {
+                Some(restored) => out.push_str(&restored),
+                None => out.push_str(&self.src[segment.range.clone()]),
+            }
+            at = segment.range.end;
+        }
+        out.push_str(&self.src[at..]);
+        out
+    }
+}
+
+impl Segment {
+    fn restore(&self, translation: &str) -> Option<String> {
+        // A segment never spans a line break it could reintroduce, and
+        // leading block markers the source did not have are escaped.
+        let mut line = translation.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            return None;
+        }
+        if self.in_table {
+            line = escape_pipes(&line);
+        }
+        let original_first = self.text.chars().next();
+        line = escape_block_start(&line, original_first);
+
+        for (placeholder, original) in &self.protected {
+            if !line.contains(placeholder.as_str()) {
+                return None;
+            }
+            line = line.replace(placeholder.as_str(), original);
+        }
+        Some(line)
+    }
+}
+
+struct Collector<'a> {
+    src: &'a str,
+    run: Option<Run>,
+    segments: Vec<Segment>,
+    /// Depth inside code blocks and autolinks, whose content is never prose.
+    skip: usize,
+    table: usize,
+}
+
+impl Collector<'_> {
+    fn event(&mut self, event: Event, range: Range<usize>) {
+        if self.skip > 0 {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::Link(..)) => self.skip += 1,
+                Event::End(Tag::CodeBlock(_)) | Event::End(Tag::Link(..)) => self.skip -= 1,
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            Event::Text(_) => {
+                let mut at = range.start;
+                for url in url_spans(&self.src[range.clone()]) {
+                    let url = url.start + range.start..url.end + range.start;
+                    if at < url.start {
+                        self.extend(at..url.start);
+                    }
+                    self.protect(url.clone());
+                    at = url.end;
+                }
+                if at < range.end {
+                    self.extend(at..range.end);
+                }
+            }
+            Event::Code(_) | Event::FootnoteReference(_) => self.protect(range),
+            // Inline HTML sits inside a run; block HTML starts outside one.
+            Event::Html(_) if self.run.is_some() && !self.src[range.clone()].contains('\n') => {
+                self.protect(range)
+            }
+            Event::SoftBreak => {}
+            Event::Start(Tag::Link(LinkType::Autolink | LinkType::Email, ..)) => {
+                self.protect(range);
+                self.skip += 1;
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.flush();
+                self.skip += 1;
+            }
+            Event::Start(
+                Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..),
+            ) => self.open(range.start),
+            Event::End(Tag::Link(..) | Tag::Image(..)) => {
+                // `](target "title")` or `][ref]` after the link text.
+                if let Some(run) = &self.run {
+                    let tail = run.range.end..range.end;
+                    if !tail.is_empty() {
+                        self.protect(tail);
+                    }
+                }
+            }
+            Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough) => {
+                if let Some(run) = self.run.as_mut() {
+                    run.range.end = run.range.end.max(range.end);
+                }
+            }
+            Event::Start(Tag::Table(_)) => {
+                self.flush();
+                self.table += 1;
+            }
+            Event::End(Tag::Table(_)) => {
+                self.flush();
+                self.table -= 1;
+            }
+            _ => self.flush(),
+        }
+    }
+
+    /// Starts a run at `start`, or keeps the current one if `start`
+    /// continues it on the same line or across a soft break.
+    fn open(&mut self, start: usize) {
+        if let Some(run) = &self.run {
+            let gap = &self.src[run.range.end.min(start)..start];
+            if gap.contains('\n') && !gap.trim().is_empty() {
+                // A soft break followed by a block prefix (`> `), which the
+                // segment must not swallow.
+                self.flush();
+            }
+        }
+        let in_table = self.table > 0;
+        self.run.get_or_insert(Run {
+            range: start..start,
+            protected: Vec::new(),
+            in_table,
+        });
+    }
+
+    fn extend(&mut self, range: Range<usize>) {
+        self.open(range.start);
+        let run = self.run.as_mut().expect("run opened");
+        run.range.end = run.range.end.max(range.end);
+    }
+
+    fn protect(&mut self, range: Range<usize>) {
+        self.extend(range.clone());
+        if let Some(run) = self.run.as_mut() {
+            run.protected.push(range);
+        }
+    }
+
+    fn flush(&mut self) {
+        let Some(run) = self.run.take() else {
+            return;
+        };
+        let mut text = String::new();
+        let mut protected = Vec::new();
+        let mut prose = false;
+        let mut at = run.range.start;
+        for span in &run.protected {
+            let before = &self.src[at..span.start];
+            prose |= before.chars().any(char::is_alphabetic);
+            text.push_str(before);
+            let placeholder = format!("{{{{M{}}}}}", protected.len() + 1);
+            text.push_str(&placeholder);
+            protected.push((placeholder, self.src[span.clone()].to_string()));
+            at = span.end;
+        }
+        let rest = &self.src[at..run.range.end];
+        prose |= rest.chars().any(char::is_alphabetic);
+        text.push_str(rest);
+
+        if !prose {
+            return;
+        }
+        self.segments.push(Segment {
+            range: run.range,
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+            protected,
+            in_table: run.in_table,
+        });
+    }
+}
+
+/// Length of a leading `---` (YAML) or `+++` (TOML) front-matter block,
+/// including its closing fence; `0` when there is none.
+fn front_matter_end(src: &str) -> usize {
+    let fence = match src.get(..3) {
+        Some(f @ ("---" | "+++")) => f,
+        _ => return 0,
+    };
+    let mut lines = src.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some(fence) {
+        return 0;
+    }
+    let mut end = fence.len() + 1;
+    for line in lines {
+        end += line.len();
+        let line = line.trim_end();
+        if line == fence || (fence == "---" && line == "...") {
+            return end.min(src.len());
+        }
+    }
+    0
+}
+
+/// Byte ranges of bare `http(s)://` URLs in plain text.
+fn url_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut from = 0;
+    while let Some(found) = ["http://", "https://"]
+        .iter()
+        .filter_map(|scheme| text[from..].find(scheme))
+        .min()
+    {
+        let start = from + found;
+        let len = text[start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"'))
+            .unwrap_or(text.len() - start);
+        // Trailing sentence punctuation belongs to the prose.
+        let url = text[start..start + len].trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+        spans.push(start..start + url.len());
+        from = start + len.max(1);
+    }
+    spans
+}
+
+/// Escapes `|` so a translated table cell cannot split into two.
+fn escape_pipes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == '|' && !escaped {
+            out.push('\\');
+        }
+        escaped = c == '\\' && !escaped;
+        out.push(c);
+    }
+    out
+}
+
+/// Escapes a leading heading, quote, list, fence or rule marker the model
+/// introduced, so a translated segment cannot turn into a new block.
+fn escape_block_start(text: &str, original_first: Option<char>) -> String {
+    let Some(first) = text.chars().next() else {
+        return String::new();
+    };
+    if Some(first) == original_first {
+        return text.to_string();
+    }
+    let rest = &text[first.len_utf8()..];
+    let marker = match first {
+        '#' | '>' => true,
+        '-' | '+' | '*' => rest.starts_with(' ') || text.chars().all(|c| c == first || c == ' '),
+        '_' => text.chars().all(|c| c == '_' || c == ' '),
+        '`' | '~' => text.starts_with("```") || text.starts_with("~~~"),
+        _ => false,
+    };
+    if marker {
+        return format!("\\{}", text);
+    }
+    // `1.` / `1)` followed by a space starts an ordered list.
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let rest = &text[digits..];
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        return format!("{}\\{}", &text[..digits], rest);
+    }
+    text.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Event;
+
+    const DOC: &str = r#"---
+title: Getting started
+tags: [intro]
+---
+
+# Getting started
+
+Install the CLI with `cargo install magicbox` and read
+the [guide](https://example.com/guide "Guide") before you begin.
+
+## Steps
+
+1. Clone the repository from https://github.com/example/magicbox.
+2. Run the **build** script:
+
+   ```sh
+   ./build.sh --release
+   ```
+
+3. Open <https://localhost:8080> in a browser.
+
+- Nested list
+  - First *item*
+  - Second item with ![logo](img/logo.png)
+
+> Quoted text that wraps
+> onto a second line.
+
+<div class="note">Raw HTML is left alone.</div>
+
+| Name | Value |
+| ---- | ----- |
+| Mode | Fast  |
+
+Setext heading
+--------------
+
+    indented code stays
+"#;
+
+    /// Block-level tags of a document, which must survive translation.
+    fn structure(src: &str) -> Vec<String> {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        options.insert(Options::ENABLE_FOOTNOTES);
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_TASKLISTS);
+        Parser::new_ext(&src[front_matter_end(src)..], options)
+            .filter_map(|event| match event {
+                Event::Start(tag) | Event::End(tag) => match tag {
+                    Tag::Emphasis
+                    | Tag::Strong
+                    | Tag::Strikethrough
+                    | Tag::Link(..)
+                    | Tag::Image(..) => None,
+                    tag => Some(format!("{:?}", tag)),
+                },
+                Event::Rule => Some("Rule".to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn translate(src: &str, f: impl Fn(&str) -> String) -> String {
+        let doc = Document::parse(src);
+        let translations: Vec<String> = doc.segments().map(f).collect();
+        doc.render(&translations)
+    }
+
+    #[test]
+    fn preserves_headings_and_lists() {
+        let out = translate(DOC, |s| s.to_uppercase());
+        assert_eq!(structure(DOC), structure(&out));
+        assert!(out.contains("# GETTING STARTED\n"));
+        assert!(out.contains("- FIRST *ITEM*"));
+        assert!(out.contains("SETEXT HEADING\n--------------"));
+    }
+
+    #[test]
+    fn hostile_translations_cannot_change_structure() {
+        let out = translate(DOC, |s| format!("# 1. - {}\n\n| > *", s));
+        assert_eq!(structure(DOC), structure(&out));
+    }
+
+    #[test]
+    fn passes_code_urls_html_and_front_matter_through() {
+        let out = translate(DOC, |s| s.to_uppercase());
+        for kept in [
+            "---\ntitle: Getting started\ntags: [intro]\n---\n",
+            "`cargo install magicbox`",
+            "](https://example.com/guide \"Guide\")",
+            "https://github.com/example/magicbox.",
+            "```sh\n   ./build.sh --release\n   ```",
+            "<https://localhost:8080>",
+            "](img/logo.png)",
+            "<div class=\"note\">Raw HTML is left alone.</div>",
+            "    indented code stays",
+        ] {
+            assert!(out.contains(kept), "missing {:?} in\n{}", kept, out);
+        }
+    }
+
+    #[test]
+    fn segments_hide_code_and_targets() {
+        let doc = Document::parse(DOC);
+        let segments: Vec<&str> = doc.segments().collect();
+        assert!(segments
+            .contains(&"Install the CLI with {{M1}} and read the [guide{{M2}} before you begin."));
+        for segment in &segments {
+            for hidden in ["cargo install", "example.com", "build.sh", "title:", "<div"] {
+                assert!(
+                    !segment.contains(hidden),
+                    "{:?} leaked into {:?}",
+                    hidden,
+                    segment
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dropped_placeholder_keeps_original_segment() {
+        let src = "Run `make` now.\n";
+        assert_eq!(translate(src, |_| "Lancez maintenant.".into()), src);
+        assert_eq!(
+            translate(src, |s| s
+                .replace("Run", "Lancez")
+                .replace("now", "maintenant")),
+            "Lancez `make` maintenant.\n"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Translation memory: every provider translation of a plain-text request,
+//! kept in a local SQLite file so it survives restarts. Exact matches are
+//! served without calling a provider; near matches are offered as
+//! suggestions.
+
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use time::OffsetDateTime;
+
+use crate::providers::Lang;
+
+pub mod tmx;
+
+/// Rows compared per fuzzy lookup, most recently updated first.
+const FUZZY_SCAN_LIMIT: usize = 5000;
+
+#[derive(Clone, Debug)]
+pub struct TmEntry {
+    pub source_lang: Lang,
+    pub target_lang: Lang,
+    pub source: String,
+    pub target: String,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    /// Unix seconds.
+    pub updated_at: i64,
+}
+
+pub struct FuzzyMatch {
+    pub entry: TmEntry,
+    /// See `similarity`.
+    pub similarity: f32,
+}
+
+pub struct TranslationMemory {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl TranslationMemory {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tm (
+                id INTEGER PRIMARY KEY,
+                source_lang TEXT NOT NULL,
+                target_lang TEXT NOT NULL,
+                source_text TEXT NOT NULL,
+                target_text TEXT NOT NULL,
+                provider TEXT,
+                model TEXT,
+                updated_at INTEGER NOT NULL,
+                UNIQUE (source_lang, target_lang, source_text)
+            );
+            CREATE INDEX IF NOT EXISTS tm_pair_length
+                ON tm (source_lang, target_lang, length(source_text));",
+        )?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Runs `f` on the blocking pool; SQLite calls must not stall the
+    /// async workers.
+    async fn with_conn<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().expect("translation memory poisoned");
+            f(&mut conn)
+        })
+        .await?;
+        Ok(result?)
+    }
+
+    pub async fn exact(
+        &self,
+        source_lang: Lang,
+        target_lang: Lang,
+        text: &str,
+    ) -> anyhow::Result<Option<TmEntry>> {
+        let text = text.to_string();
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT source_lang, target_lang, source_text, target_text, provider, model, updated_at
+                 FROM tm WHERE source_lang = ?1 AND target_lang = ?2 AND source_text = ?3",
+                params![source_lang.as_str(), target_lang.as_str(), text],
+                entry_from_row,
+            )
+            .optional()
+            .map(Option::flatten)
+        })
+        .await
+    }
+
+    /// Inserts or replaces entries keyed by language pair and source text.
+    /// Returns the number written.
+    pub async fn store(&self, entries: Vec<TmEntry>) -> anyhow::Result<usize> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT INTO tm (source_lang, target_lang, source_text, target_text, provider, model, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT (source_lang, target_lang, source_text) DO UPDATE SET
+                         target_text = excluded.target_text,
+                         provider = excluded.provider,
+                         model = excluded.model,
+                         updated_at = excluded.updated_at",
+                )?;
+                for entry in &entries {
+                    stmt.execute(params![
+                        entry.source_lang.as_str(),
+                        entry.target_lang.as_str(),
+                        entry.source,
+                        entry.target,
+                        entry.provider,
+                        entry.model,
+                        entry.updated_at,
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(entries.len())
+        })
+        .await
+    }
+
+    /// Deletes the entry for `text`, only if it came from `provider` when
+    /// one is given. Returns the number removed.
+    pub async fn remove(
+        &self,
+        source_lang: Lang,
+        target_lang: Lang,
+        text: &str,
+        provider: Option<&str>,
+    ) -> anyhow::Result<u64> {
+        let text = text.to_string();
+        let provider = provider.map(str::to_string);
+        self.with_conn(move |conn| {
+            conn.execute(
+                "DELETE FROM tm
+                 WHERE source_lang = ?1 AND target_lang = ?2 AND source_text = ?3
+                   AND (?4 IS NULL OR provider = ?4)",
+                params![source_lang.as_str(), target_lang.as_str(), text, provider],
+            )
+            .map(|n| n as u64)
+        })
+        .await
+    }
+
+    /// Deletes every entry translated by `provider`. Imported entries
+    /// without a provider are kept.
+    pub async fn remove_provider(&self, provider: &str) -> anyhow::Result<u64> {
+        let provider = provider.to_string();
+        self.with_conn(move |conn| {
+            conn.execute("DELETE FROM tm WHERE provider = ?1", params![provider])
+                .map(|n| n as u64)
+        })
+        .await
+    }
+
+    /// Entries whose source is within `min_similarity` of `text`, best
+    /// first.
+    pub async fn fuzzy(
+        &self,
+        source_lang: Lang,
+        target_lang: Lang,
+        text: &str,
+        min_similarity: f32,
+        limit: usize,
+    ) -> anyhow::Result<Vec<FuzzyMatch>> {
+        let query: Vec<char> = text.chars().collect();
+        // An edit distance of at least the length difference bounds which
+        // lengths can reach `min_similarity` at all.
+        let len = query.len() as f32;
+        let min_len = (len * min_similarity).ceil() as i64;
+        let max_len = (len / min_similarity.max(0.01)).floor() as i64;
+
+        let candidates = self
+            .with_conn(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT source_lang, target_lang, source_text, target_text, provider, model, updated_at
+                     FROM tm
+                     WHERE source_lang = ?1 AND target_lang = ?2
+                       AND length(source_text) BETWEEN ?3 AND ?4
+                     ORDER BY updated_at DESC LIMIT ?5",
+                )?;
+                let rows = stmt.query_map(
+                    params![
+                        source_lang.as_str(),
+                        target_lang.as_str(),
+                        min_len,
+                        max_len,
+                        FUZZY_SCAN_LIMIT as i64
+                    ],
+                    entry_from_row,
+                )?;
+                rows.filter_map(Result::transpose).collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await?;
+
+        // Thousands of edit distances take too long for an async worker.
+        let matches = tokio::task::spawn_blocking(move || {
+            let mut matches: Vec<FuzzyMatch> = candidates
+                .into_iter()
+                .filter_map(|entry| {
+                    let candidate: Vec<char> = entry.source.chars().collect();
+                    let similarity = similarity(&query, &candidate);
+                    (similarity >= min_similarity).then_some(FuzzyMatch { entry, similarity })
+                })
+                .collect();
+            matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+            matches.truncate(limit);
+            matches
+        })
+        .await?;
+        Ok(matches)
+    }
+
+    /// All entries, optionally for one language pair, oldest first.
+    pub async fn export(
+        &self,
+        source_lang: Option<Lang>,
+        target_lang: Option<Lang>,
+    ) -> anyhow::Result<Vec<TmEntry>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT source_lang, target_lang, source_text, target_text, provider, model, updated_at
+                 FROM tm
+                 WHERE (?1 IS NULL OR source_lang = ?1) AND (?2 IS NULL OR target_lang = ?2)
+                 ORDER BY id",
+            )?;
+            let rows = stmt.query_map(
+                params![
+                    source_lang.map(|l| l.as_str()),
+                    target_lang.map(|l| l.as_str())
+                ],
+                entry_from_row,
+            )?;
+            rows.filter_map(Result::transpose).collect()
+        })
+        .await
+    }
+}
+
+pub fn now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+/// `None` for rows whose language is no longer supported.
+fn entry_from_row(row: &Row) -> rusqlite::Result<Option<TmEntry>> {
+    let source_lang: String = row.get(0)?;
+    let target_lang: String = row.get(1)?;
+    let (Ok(source_lang), Ok(target_lang)) =
+        (Lang::from_str(&source_lang), Lang::from_str(&target_lang))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(TmEntry {
+        source_lang,
+        target_lang,
+        source: row.get(2)?,
+        target: row.get(3)?,
+        provider: row.get(4)?,
+        model: row.get(5)?,
+        updated_at: row.get(6)?,
+    }))
+}
+
+/// `1 - edit distance / length of the longer text`; 1.0 for two empty texts.
+pub fn similarity(a: &[char], b: &[char]) -> f32 {
+    let longest = a.len().max(b.len()).max(1);
+    1.0 - levenshtein(a, b) as f32 / longest as f32
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
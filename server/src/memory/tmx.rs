@@ -0,0 +1,237 @@
+//! TMX 1.4 import and export.
+//!
+//! Export writes one `<tu>` per entry with the provider and model as
+//! `x-provider` / `x-model` props. Import accepts any TMX: each `<tu>` yields
+//! one entry per target variant, from the header's `srclang` variant (or the
+//! first one) to every other variant in a supported language.
+
+use std::str::FromStr;
+
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader,
+};
+use time::{format_description::FormatItem, macros::format_description, OffsetDateTime};
+
+use super::{now, TmEntry};
+use crate::providers::Lang;
+
+const TMX_DATE: &[FormatItem<'static>] =
+    format_description!("[year][month][day]T[hour][minute][second]Z");
+
+/// Elements inside `<seg>` that hold native markup codes, not text.
+const CODE_ELEMENTS: [&[u8]; 5] = [b"bpt", b"ept", b"ph", b"it", b"ut"];
+
+pub struct Imported {
+    pub entries: Vec<TmEntry>,
+    /// Variants skipped because their language is unsupported or a unit
+    /// had no usable source.
+    pub skipped: usize,
+}
+
+#[derive(Default)]
+struct Unit {
+    variants: Vec<(String, String)>,
+    provider: Option<String>,
+    model: Option<String>,
+    date: Option<i64>,
+}
+
+pub fn parse(xml: &str) -> Result<Imported, quick_xml::Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut srclang: Option<String> = None;
+    let mut unit: Option<Unit> = None;
+    let mut lang: Option<String> = None;
+    let mut prop: Option<String> = None;
+    let mut seg: Option<String> = None;
+    let mut code_depth = 0usize;
+    let mut text = String::new();
+    let mut imported = Imported {
+        entries: Vec::new(),
+        skipped: 0,
+    };
+
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(e) | Event::Empty(e) if e.name().as_ref() == b"header" => {
+                srclang = attr(&e, b"srclang").filter(|l| l != "*all*");
+            }
+            Event::Start(e) if seg.is_some() && CODE_ELEMENTS.contains(&e.name().as_ref()) => {
+                code_depth += 1;
+            }
+            Event::End(e) if seg.is_some() && CODE_ELEMENTS.contains(&e.name().as_ref()) => {
+                code_depth = code_depth.saturating_sub(1);
+            }
+            Event::Start(e) => match e.name().as_ref() {
+                b"tu" => {
+                    unit = Some(Unit {
+                        date: attr(&e, b"changedate")
+                            .or_else(|| attr(&e, b"creationdate"))
+                            .and_then(|d| parse_date(&d)),
+                        ..Unit::default()
+                    })
+                }
+                b"tuv" => lang = attr(&e, b"xml:lang").or_else(|| attr(&e, b"lang")),
+                b"prop" => {
+                    prop = attr(&e, b"type");
+                    text.clear();
+                }
+                b"seg" => seg = Some(String::new()),
+                _ => {}
+            },
+            Event::Text(e) => {
+                let value = e.unescape()?;
+                match seg.as_mut() {
+                    Some(seg) if code_depth == 0 => seg.push_str(&value),
+                    Some(_) => {}
+                    None => text.push_str(&value),
+                }
+            }
+            Event::CData(e) => {
+                if let Some(seg) = seg.as_mut().filter(|_| code_depth == 0) {
+                    seg.push_str(&String::from_utf8_lossy(&e));
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"seg" => {
+                    if let (Some(unit), Some(lang), Some(seg)) =
+                        (unit.as_mut(), lang.clone(), seg.take())
+                    {
+                        unit.variants.push((lang, seg));
+                    }
+                    code_depth = 0;
+                }
+                b"prop" => {
+                    if let (Some(unit), Some(kind)) = (unit.as_mut(), prop.take()) {
+                        match kind.as_str() {
+                            "x-provider" => unit.provider = Some(text.trim().to_string()),
+                            "x-model" => unit.model = Some(text.trim().to_string()),
+                            _ => {}
+                        }
+                    }
+                }
+                b"tu" => {
+                    if let Some(unit) = unit.take() {
+                        collect(unit, srclang.as_deref(), &mut imported);
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    Ok(imported)
+}
+
+fn collect(unit: Unit, srclang: Option<&str>, imported: &mut Imported) {
+    let source_index = srclang
+        .and_then(|src| {
+            unit.variants
+                .iter()
+                .position(|(lang, _)| same_lang(lang, src))
+        })
+        .unwrap_or(0);
+    let Some((source_lang, source)) = unit.variants.get(source_index) else {
+        return;
+    };
+    let Ok(source_lang) = Lang::from_str(source_lang) else {
+        imported.skipped += unit.variants.len().saturating_sub(1).max(1);
+        return;
+    };
+
+    for (i, (lang, target)) in unit.variants.iter().enumerate() {
+        if i == source_index {
+            continue;
+        }
+        match Lang::from_str(lang) {
+            Ok(target_lang) if target_lang != source_lang && !source.trim().is_empty() => {
+                imported.entries.push(TmEntry {
+                    source_lang,
+                    target_lang,
+                    source: source.trim().to_string(),
+                    target: target.trim().to_string(),
+                    provider: unit.provider.clone(),
+                    model: unit.model.clone(),
+                    updated_at: unit.date.unwrap_or_else(now),
+                })
+            }
+            _ => imported.skipped += 1,
+        }
+    }
+}
+
+pub fn write(entries: &[TmEntry]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<tmx version=\"1.4\">\n",
+        "  <header creationtool=\"MagicBox\" creationtoolversion=\"",
+        env!("CARGO_PKG_VERSION"),
+        "\" datatype=\"plaintext\" segtype=\"sentence\" adminlang=\"en\" srclang=\"*all*\" o-tmf=\"MagicBox\"/>\n",
+        "  <body>\n",
+    ));
+    for entry in entries {
+        out.push_str(&format!(
+            "    <tu changedate=\"{}\">\n",
+            format_date(entry.updated_at)
+        ));
+        if let Some(provider) = &entry.provider {
+            out.push_str(&format!(
+                "      <prop type=\"x-provider\">{}</prop>\n",
+                escape(provider.as_str())
+            ));
+        }
+        if let Some(model) = &entry.model {
+            out.push_str(&format!(
+                "      <prop type=\"x-model\">{}</prop>\n",
+                escape(model.as_str())
+            ));
+        }
+        for (lang, text) in [
+            (entry.source_lang, &entry.source),
+            (entry.target_lang, &entry.target),
+        ] {
+            out.push_str(&format!(
+                "      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n",
+                lang.as_str(),
+                escape(text.as_str())
+            ));
+        }
+        out.push_str("    </tu>\n");
+    }
+    out.push_str("  </body>\n</tmx>\n");
+    out
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+/// Compares primary language subtags, so `en-US` matches `EN`.
+fn same_lang(a: &str, b: &str) -> bool {
+    let primary = |l: &str| {
+        l.split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    };
+    primary(a) == primary(b)
+}
+
+fn parse_date(value: &str) -> Option<i64> {
+    time::PrimitiveDateTime::parse(value, TMX_DATE)
+        .ok()
+        .map(|d| d.assume_utc().unix_timestamp())
+}
+
+fn format_date(unix: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(unix)
+        .ok()
+        .and_then(|d| d.format(TMX_DATE).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,214 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, warn};
+
+use super::{DeltaStream, Lang, ProviderError, TranslateOptions, TranslateProvider, Usage};
+
+/// Ordered list of providers. A request goes to the first provider whose
+/// circuit is closed and falls through to the next one on retryable errors
+/// (transport failures, timeouts, 429/5xx) or when a provider reports the
+/// request as unsupported.
+pub struct ProviderChain {
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    provider: Arc<dyn TranslateProvider>,
+    breaker: CircuitBreaker,
+}
+
+pub struct Translated {
+    pub text: String,
+    /// Name of the provider that actually answered.
+    pub provider: String,
+    pub model: Option<String>,
+    /// Tokens spent producing `text`; `None` for results not fresh from a
+    /// provider call, or when the upstream does not report usage.
+    pub usage: Option<Usage>,
+}
+
+pub struct TranslatedStream {
+    pub deltas: DeltaStream,
+    /// Name of the provider that accepted the request.
+    pub provider: String,
+    pub model: Option<String>,
+}
+
+impl ProviderChain {
+    pub fn new(
+        providers: Vec<Arc<dyn TranslateProvider>>,
+        failure_threshold: u32,
+        cooldown: Duration,
+    ) -> Self {
+        let entries = providers
+            .into_iter()
+            .map(|provider| Entry {
+                provider,
+                breaker: CircuitBreaker::new(failure_threshold, cooldown),
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// Provider names in fallback order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.provider.name())
+    }
+
+    /// Set when every provider has a limitation, since then nothing in the
+    /// chain covers what they decline.
+    pub fn degraded(&self) -> Option<String> {
+        let limits: Option<Vec<String>> = self
+            .entries
+            .iter()
+            .map(|e| {
+                let limit = e.provider.limitation()?;
+                Some(format!("{}: {}", e.provider.name(), limit))
+            })
+            .collect();
+        limits.map(|limits| limits.join("; "))
+    }
+
+    pub async fn translate(
+        &self,
+        text: &str,
+        source: Option<Lang>,
+        target: Lang,
+        options: TranslateOptions<'_>,
+    ) -> Result<Translated, ProviderError> {
+        let (out, provider) = self
+            .first_accepting(|p| p.translate(text, source, target, options))
+            .await?;
+        Ok(Translated {
+            text: out.text,
+            provider: provider.name().to_string(),
+            model: provider.model().map(str::to_string),
+            usage: out.usage,
+        })
+    }
+
+    /// Like `translate`, but falls through only while establishing the
+    /// stream; once a provider has accepted the request its errors are
+    /// delivered in-band and do not trip its breaker.
+    pub async fn translate_stream(
+        &self,
+        text: &str,
+        source: Option<Lang>,
+        target: Lang,
+        options: TranslateOptions<'_>,
+    ) -> Result<TranslatedStream, ProviderError> {
+        let (deltas, provider) = self
+            .first_accepting(|p| p.translate_stream(text, source, target, options))
+            .await?;
+        Ok(TranslatedStream {
+            deltas,
+            provider: provider.name().to_string(),
+            model: provider.model().map(str::to_string),
+        })
+    }
+
+    /// Runs `call` against each provider whose circuit lets it through,
+    /// until one succeeds or fails in a way falling through cannot help.
+    async fn first_accepting<'a, T, F, Fut>(
+        &'a self,
+        mut call: F,
+    ) -> Result<(T, &'a dyn TranslateProvider), ProviderError>
+    where
+        F: FnMut(&'a dyn TranslateProvider) -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let mut last_err = None;
+        for entry in &self.entries {
+            let provider = &*entry.provider;
+            let name = provider.name();
+            if !entry.breaker.allow() {
+                warn!(provider = name, "circuit open, skipping provider");
+                continue;
+            }
+            match call(provider).await {
+                Ok(out) => {
+                    entry.breaker.record_success();
+                    return Ok((out, provider));
+                }
+                Err(err) if err.is_retryable() => {
+                    entry.breaker.record_failure();
+                    warn!(provider = name, error = %err, "provider failed, falling through");
+                    last_err = Some(err);
+                }
+                // The provider is healthy, it just cannot take this request.
+                Err(err @ ProviderError::Unsupported(_)) => {
+                    entry.breaker.record_success();
+                    debug!(provider = name, error = %err, "provider declined, falling through");
+                    last_err = Some(err);
+                }
+                // The provider answered, so a half-open circuit closes; the
+                // request itself is at fault and would fail elsewhere too.
+                Err(err) => {
+                    entry.breaker.record_success();
+                    return Err(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(all_open))
+    }
+}
+
+fn all_open() -> ProviderError {
+    ProviderError::Unavailable("all provider circuits are open".into())
+}
+
+/// Opens after `threshold` consecutive failures and rejects calls until the
+/// cool-down passes. The first call after that is a probe: success closes
+/// the circuit, another failure re-opens it for a full cool-down.
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().expect("breaker lock");
+        match state.open_until {
+            None => true,
+            Some(until) if Instant::now() >= until => {
+                // Half-open: let this caller probe and keep everyone else out
+                // until it reports back.
+                state.open_until = Some(Instant::now() + self.cooldown);
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().expect("breaker lock");
+        *state = BreakerState::default();
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().expect("breaker lock");
+        state.failures = state.failures.saturating_add(1);
+        if state.failures >= self.threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
@@ -0,0 +1,498 @@
+//! Phrase-dictionary fallback for air-gapped deployments. This is not a
+//! translation model: it only translates text whose every word is covered
+//! by a dictionary, and declines the rest as unsupported so a chain can
+//! hand it on. Needs neither network nor model files.
+//!
+//! Each line is tokenized (words for spaced scripts, single characters for
+//! Han, Kana and Thai) and the longest dictionary phrase is matched first.
+//! The built-in English ↔ Chinese dictionary in `dict/` holds about 135
+//! UI phrases and is compiled into the binary; more pairs come from
+//! `<source>-<target>.tsv` and TMX files in `DICTIONARY_DIR`. Every entry is
+//! also used in the reverse direction unless that direction has its own.
+
+use std::{borrow::Cow, collections::HashMap, ops::Range, path::Path, str::FromStr};
+
+use async_trait::async_trait;
+use tracing::info;
+
+use super::{Lang, ProviderError, TranslateOptions, TranslateProvider, Translation};
+use crate::{detect::Script, memory::tmx};
+
+/// Untranslated words quoted in the `Unsupported` error.
+const MISSING_SHOWN: usize = 5;
+
+const BUILTIN: [(Lang, Lang, &str); 1] = [(Lang::En, Lang::Zh, include_str!("dict/en-zh.tsv"))];
+
+pub struct DictionaryProvider {
+    dictionaries: HashMap<(Lang, Lang), Dictionary>,
+}
+
+#[derive(Default)]
+struct Dictionary {
+    /// Lower-cased source tokens joined by spaces → translation.
+    phrases: HashMap<String, String>,
+    /// Longest phrase, in tokens.
+    max_tokens: usize,
+}
+
+impl Dictionary {
+    fn insert(&mut self, source: &str, target: &str, overwrite: bool) {
+        let tokens = tokenize(source);
+        let target = target.trim();
+        if tokens.is_empty() || target.is_empty() {
+            return;
+        }
+        let key = phrase_key(source, &tokens);
+        if overwrite || !self.phrases.contains_key(&key) {
+            self.phrases.insert(key, target.to_string());
+            self.max_tokens = self.max_tokens.max(tokens.len());
+        }
+    }
+}
+
+impl DictionaryProvider {
+    /// Only the built-in dictionaries.
+    pub fn builtin() -> Self {
+        let mut provider = Self {
+            dictionaries: HashMap::new(),
+        };
+        for (source, target, raw) in BUILTIN {
+            provider.add_tsv(source, target, raw);
+        }
+        provider
+    }
+
+    /// Adds every `.tsv` and `.tmx` file in `dir`; later entries replace
+    /// built-in ones.
+    pub fn load_dir(&mut self, dir: &Path) -> anyhow::Result<()> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .map_err(|e| anyhow::anyhow!("read {}: {}", dir.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let ext = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_ascii_lowercase);
+            let read = || {
+                std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow::anyhow!("read {}: {}", path.display(), e))
+            };
+            match ext.as_deref() {
+                Some("tsv") => {
+                    let (source, target) = pair_from_filename(&path)?;
+                    self.add_tsv(source, target, &read()?);
+                }
+                Some("tmx") => {
+                    let imported = tmx::parse(&read()?)
+                        .map_err(|e| anyhow::anyhow!("parse {}: {}", path.display(), e))?;
+                    for entry in imported.entries {
+                        self.add(
+                            entry.source_lang,
+                            entry.target_lang,
+                            &entry.source,
+                            &entry.target,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn add_tsv(&mut self, source: Lang, target: Lang, raw: &str) {
+        for line in raw.lines() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            if let Some((from, to)) = line.split_once('\t') {
+                self.add(source, target, from, to);
+            }
+        }
+    }
+
+    fn add(&mut self, source_lang: Lang, target_lang: Lang, source: &str, target: &str) {
+        if source_lang == target_lang {
+            return;
+        }
+        self.dictionaries
+            .entry((source_lang, target_lang))
+            .or_default()
+            .insert(source, target, true);
+        self.dictionaries
+            .entry((target_lang, source_lang))
+            .or_default()
+            .insert(target, source, false);
+    }
+
+    /// `(source, target, entries)` per loaded direction.
+    pub fn pairs(&self) -> impl Iterator<Item = (Lang, Lang, usize)> + '_ {
+        self.dictionaries
+            .iter()
+            .map(|((source, target), dict)| (*source, *target, dict.phrases.len()))
+    }
+}
+
+/// `en-zh.tsv` → `(En, Zh)`.
+fn pair_from_filename(path: &Path) -> anyhow::Result<(Lang, Lang)> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    stem.split_once('-')
+        .and_then(|(s, t)| Some((Lang::from_str(s).ok()?, Lang::from_str(t).ok()?)))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{}: dictionary files are named <source>-<target>.tsv, e.g. en-zh.tsv",
+                path.display()
+            )
+        })
+}
+
+#[async_trait]
+impl TranslateProvider for DictionaryProvider {
+    async fn translate(
+        &self,
+        text: &str,
+        source: Option<Lang>,
+        target: Lang,
+        _options: TranslateOptions<'_>,
+    ) -> Result<Translation, ProviderError> {
+        let source = source
+            .ok_or_else(|| ProviderError::Unsupported("source language is required".into()))?;
+        if source == target {
+            return Ok(Translation {
+                text: text.to_string(),
+                usage: None,
+            });
+        }
+        let dict = self.dictionaries.get(&(source, target)).ok_or_else(|| {
+            ProviderError::Unsupported(format!(
+                "no dictionary for {} -> {}",
+                source.as_str(),
+                target.as_str()
+            ))
+        })?;
+
+        let mut missing: Vec<&str> = Vec::new();
+        let lines: Vec<String> = text
+            .split('\n')
+            .map(|line| translate_line(dict, line, source, target, &mut missing))
+            .collect();
+        // Half-translated text would be cached as a translation; let the next
+        // provider in the chain take it instead.
+        if !missing.is_empty() {
+            let shown: Vec<&str> = missing.iter().take(MISSING_SHOWN).copied().collect();
+            return Err(ProviderError::Unsupported(format!(
+                "no dictionary entry for: {}{}",
+                shown.join(", "),
+                if missing.len() > MISSING_SHOWN {
+                    ", …"
+                } else {
+                    ""
+                }
+            )));
+        }
+        Ok(Translation {
+            text: lines.join("\n"),
+            usage: None,
+        })
+    }
+
+    fn name(&self) -> &str {
+        "dictionary"
+    }
+
+    fn limitation(&self) -> Option<String> {
+        let mut pairs: Vec<String> = self
+            .pairs()
+            .map(|(source, target, entries)| {
+                format!("{}-{} ({})", source.as_str(), target.as_str(), entries)
+            })
+            .collect();
+        pairs.sort();
+        Some(format!(
+            "phrase dictionary only, translates text fully covered by: {}",
+            pairs.join(", ")
+        ))
+    }
+}
+
+/// Returns the translated line. Words without a dictionary entry are kept
+/// as they are and added to `missing`; numbers and glossary placeholders
+/// need no entry.
+fn translate_line<'a>(
+    dict: &Dictionary,
+    line: &'a str,
+    source: Lang,
+    target: Lang,
+    missing: &mut Vec<&'a str>,
+) -> String {
+    let tokens = tokenize(line);
+    let lower: Vec<String> = tokens
+        .iter()
+        .map(|r| line[r.clone()].to_lowercase())
+        .collect();
+    let spaced = uses_spaces(target);
+    let mut out = String::with_capacity(line.len());
+    let mut pos = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let longest = dict.max_tokens.min(tokens.len() - i);
+        let found = (1..=longest).rev().find_map(|n| {
+            // A phrase never spans punctuation.
+            let contiguous =
+                (i + 1..i + n).all(|k| line[tokens[k - 1].end..tokens[k].start].trim().is_empty());
+            if !contiguous {
+                return None;
+            }
+            dict.phrases
+                .get(&lower[i..i + n].join(" "))
+                .map(|translation| (n, translation))
+        });
+        let (n, piece) = match found {
+            Some((n, translation)) => {
+                let span = &line[tokens[i].start..tokens[i + n - 1].end];
+                (n, adapt_case(span, translation, spaced))
+            }
+            None => {
+                let word = &line[tokens[i].clone()];
+                let placeholder = line[..tokens[i].start].ends_with("{{")
+                    && line[tokens[i].end..].starts_with("}}");
+                if word.chars().any(char::is_alphabetic) && !placeholder && !missing.contains(&word)
+                {
+                    missing.push(word);
+                }
+                (1, Cow::Borrowed(word))
+            }
+        };
+        push_gap(&mut out, &line[pos..tokens[i].start], &piece, spaced);
+        out.push_str(&piece);
+        pos = tokens[i + n - 1].end;
+        i += n;
+    }
+    push_gap(&mut out, &line[pos..], "", spaced);
+    // Scripts without case give no hint where capitals belong.
+    if spaced && !uses_spaces(source) {
+        out = capitalize_sentences(&out);
+    }
+    out
+}
+
+/// Upper-cases the first letter of the line and of every sentence after
+/// `.`, `!` or `?`.
+fn capitalize_sentences(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut start = true;
+    for c in text.chars() {
+        if start && c.is_alphabetic() {
+            out.extend(c.to_uppercase());
+            start = false;
+            continue;
+        }
+        if matches!(c, '.' | '!' | '?') {
+            start = true;
+        } else if !c.is_whitespace() {
+            start = false;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Byte ranges of the words in `text`; Han, Kana and Thai characters are
+/// tokens on their own since those scripts do not separate words.
+fn tokenize(text: &str) -> Vec<Range<usize>> {
+    let mut tokens = Vec::new();
+    let mut word: Option<usize> = None;
+    for (i, c) in text.char_indices() {
+        let single = matches!(
+            Script::of(c),
+            Some(Script::Han | Script::Kana | Script::Thai)
+        );
+        if c.is_alphanumeric() && !single {
+            word.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = word.take() {
+            tokens.push(start..i);
+        }
+        if single {
+            tokens.push(i..i + c.len_utf8());
+        }
+    }
+    if let Some(start) = word {
+        tokens.push(start..text.len());
+    }
+    tokens
+}
+
+fn phrase_key(text: &str, tokens: &[Range<usize>]) -> String {
+    tokens
+        .iter()
+        .map(|r| text[r.clone()].to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn uses_spaces(lang: Lang) -> bool {
+    !matches!(lang, Lang::Zh | Lang::Ja | Lang::Th)
+}
+
+/// Carries capitalisation over for scripts that have case: `Save` → `Save`
+/// rather than `save`, `OK` stays upper case.
+fn adapt_case<'a>(source: &str, translation: &'a str, spaced: bool) -> Cow<'a, str> {
+    if !spaced {
+        return Cow::Borrowed(translation);
+    }
+    let letters: Vec<char> = source.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() > 1 && letters.iter().all(|c| c.is_uppercase()) {
+        return Cow::Owned(translation.to_uppercase());
+    }
+    match (source.chars().next(), translation.chars().next()) {
+        (Some(s), Some(t)) if s.is_uppercase() && t.is_lowercase() => {
+            let mut out: String = t.to_uppercase().collect();
+            out.push_str(&translation[t.len_utf8()..]);
+            Cow::Owned(out)
+        }
+        _ => Cow::Borrowed(translation),
+    }
+}
+
+/// Appends the text between two tokens, adjusting spaces and sentence
+/// punctuation to the target script. `next` is what follows (empty at the
+/// end of the line).
+fn push_gap(out: &mut String, gap: &str, next: &str, spaced: bool) {
+    let prev = out.chars().last();
+    let first = next.chars().next();
+    if gap.trim().is_empty() {
+        let wide = |c: Option<char>| c.is_some_and(|c| !c.is_ascii());
+        if !spaced && !gap.is_empty() && wide(prev) && wide(first) {
+            return;
+        }
+        let alnum = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+        if spaced && gap.is_empty() && alnum(prev) && alnum(first) {
+            out.push(' ');
+            return;
+        }
+        out.push_str(gap);
+        return;
+    }
+
+    let chars: Vec<char> = gap.chars().collect();
+    let mut skip_space = false;
+    for (k, &c) in chars.iter().enumerate() {
+        if skip_space && c.is_whitespace() {
+            continue;
+        }
+        skip_space = false;
+        let at_end = k + 1 == chars.len();
+        let followed_by_space = chars.get(k + 1).is_some_and(|c| c.is_whitespace());
+        if spaced {
+            match fullwidth_to_ascii(c) {
+                Some(ascii) => {
+                    out.push(ascii);
+                    if !(followed_by_space || at_end && next.is_empty()) {
+                        out.push(' ');
+                    }
+                }
+                None => out.push(c),
+            }
+        } else {
+            match ascii_to_fullwidth(c) {
+                Some(wide) if followed_by_space || (at_end && next.is_empty()) => {
+                    out.push(wide);
+                    skip_space = true;
+                }
+                _ => out.push(c),
+            }
+        }
+    }
+}
+
+fn fullwidth_to_ascii(c: char) -> Option<char> {
+    Some(match c {
+        '，' | '、' => ',',
+        '。' => '.',
+        '！' => '!',
+        '？' => '?',
+        '：' => ':',
+        '；' => ';',
+        _ => return None,
+    })
+}
+
+fn ascii_to_fullwidth(c: char) -> Option<char> {
+    Some(match c {
+        ',' => '，',
+        '.' => '。',
+        '!' => '！',
+        '?' => '？',
+        ':' => '：',
+        ';' => '；',
+        _ => return None,
+    })
+}
+
+/// `TRANSLATE_PROVIDER=dictionary`.
+pub fn from_env() -> anyhow::Result<DictionaryProvider> {
+    let mut provider = DictionaryProvider::builtin();
+    if let Some(dir) = std::env::var("DICTIONARY_DIR")
+        .ok()
+        .filter(|d| !d.trim().is_empty())
+    {
+        provider.load_dir(Path::new(&dir))?;
+    }
+    let entries: usize = provider.pairs().map(|(_, _, n)| n).sum();
+    info!(
+        directions = provider.dictionaries.len(),
+        entries, "dictionaries loaded"
+    );
+    Ok(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run(text: &str, source: Lang, target: Lang) -> Result<Translation, ProviderError> {
+        DictionaryProvider::builtin()
+            .translate(text, Some(source), target, TranslateOptions::default())
+            .await
+    }
+
+    #[tokio::test]
+    async fn full_hit() {
+        let out = run("你好，世界！请保存文件。", Lang::Zh, Lang::En).await;
+        assert_eq!(out.unwrap().text, "Hello, world! Please save file.");
+        let out = run("Save 3 files {{G1}}", Lang::En, Lang::Zh).await;
+        assert_eq!(out.unwrap().text, "保存 3 文件 {{G1}}");
+    }
+
+    #[tokio::test]
+    async fn partial_hit_is_unsupported() {
+        let err = run("Save the file, please.", Lang::En, Lang::Zh)
+            .await
+            .err()
+            .expect("partial coverage");
+        assert!(
+            matches!(&err, ProviderError::Unsupported(m) if m.ends_with(": the")),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn miss_is_unsupported() {
+        for (text, source, target) in [
+            ("quantum chromodynamics", Lang::En, Lang::Zh),
+            ("hello", Lang::En, Lang::De),
+        ] {
+            assert!(matches!(
+                run(text, source, target).await,
+                Err(ProviderError::Unsupported(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Deterministic provider for development and tests: no network, no key,
+//! and the same output for the same input every time.
+
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use async_trait::async_trait;
+
+use super::{Lang, ProviderError, TranslateOptions, TranslateProvider, Translation, Usage};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MockMode {
+    /// Returns the text unchanged.
+    Echo,
+    /// Upper-cases the text; placeholders and segment markers survive, so
+    /// glossaries and packing behave as with a real model.
+    Upper,
+    /// Reverses the characters of each line.
+    Reverse,
+    /// Looks the text up in the fixtures, as a whole or else line by line
+    /// (lines without letters, such as pack markers, pass through); misses
+    /// are `Unsupported`.
+    Fixture,
+}
+
+impl FromStr for MockMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "echo" => Ok(MockMode::Echo),
+            "upper" => Ok(MockMode::Upper),
+            "reverse" => Ok(MockMode::Reverse),
+            "fixture" => Ok(MockMode::Fixture),
+            other => Err(anyhow::anyhow!(
+                "unsupported MOCK_MODE: {} (expected echo, upper, reverse or fixture)",
+                other
+            )),
+        }
+    }
+}
+
+pub struct MockProvider {
+    name: String,
+    mode: MockMode,
+    /// Trimmed source text → translation.
+    fixtures: HashMap<String, String>,
+}
+
+impl MockProvider {
+    pub fn new(name: impl Into<String>, mode: MockMode) -> Self {
+        Self {
+            name: name.into(),
+            mode,
+            fixtures: HashMap::new(),
+        }
+    }
+
+    pub fn with_fixtures(mut self, fixtures: HashMap<String, String>) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    fn lookup(&self, text: &str) -> Result<String, ProviderError> {
+        if let Some(out) = self.fixtures.get(text.trim()) {
+            return Ok(out.clone());
+        }
+        let miss = || ProviderError::Unsupported(format!("no mock fixture for {:?}", text.trim()));
+        if !text.contains('\n') {
+            return Err(miss());
+        }
+        text.split('\n')
+            .map(|line| {
+                if !line.chars().any(char::is_alphabetic) {
+                    return Some(line.to_string());
+                }
+                self.fixtures.get(line.trim()).cloned()
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|lines| lines.join("\n"))
+            .ok_or_else(miss)
+    }
+}
+
+#[async_trait]
+impl TranslateProvider for MockProvider {
+    async fn translate(
+        &self,
+        text: &str,
+        _source: Option<Lang>,
+        _target: Lang,
+        _options: TranslateOptions<'_>,
+    ) -> Result<Translation, ProviderError> {
+        let out = match self.mode {
+            MockMode::Echo => text.to_string(),
+            MockMode::Upper => text.to_uppercase(),
+            MockMode::Reverse => text
+                .split('\n')
+                .map(|line| line.chars().rev().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            MockMode::Fixture => self.lookup(text)?,
+        };
+        // One token per character, so usage accounting can be checked.
+        let prompt_tokens = text.chars().count() as u64;
+        let completion_tokens = out.chars().count() as u64;
+        Ok(Translation {
+            text: out,
+            usage: Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            }),
+        })
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> Option<&str> {
+        Some("mock")
+    }
+}
+
+/// `TRANSLATE_PROVIDER=mock`: `MOCK_MODE` (default `echo`), `MOCK_FIXTURES`
+/// (JSON object of source text → translation, required for `fixture`).
+pub fn from_env() -> anyhow::Result<MockProvider> {
+    let mode: MockMode = std::env::var("MOCK_MODE")
+        .unwrap_or_else(|_| "echo".to_string())
+        .parse()?;
+    let fixtures = match std::env::var("MOCK_FIXTURES") {
+        Ok(path) => load_fixtures(Path::new(&path))?,
+        Err(_) if mode == MockMode::Fixture => {
+            return Err(anyhow::anyhow!("MOCK_MODE=fixture needs MOCK_FIXTURES"))
+        }
+        Err(_) => HashMap::new(),
+    };
+    Ok(MockProvider::new("mock", mode).with_fixtures(fixtures))
+}
+
+fn load_fixtures(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("read {}: {}", path.display(), e))?;
+    let fixtures: HashMap<String, String> = serde_json::from_str(&raw)
+        .map_err(|e| anyhow::anyhow!("parse {}: {}", path.display(), e))?;
+    Ok(fixtures
+        .into_iter()
+        .map(|(source, target)| (source.trim().to_string(), target))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run(provider: &MockProvider, text: &str) -> Result<Translation, ProviderError> {
+        provider
+            .translate(text, Some(Lang::En), Lang::Zh, TranslateOptions::default())
+            .await
+    }
+
+    #[tokio::test]
+    async fn modes_are_deterministic() {
+        let upper = MockProvider::new("mock", MockMode::Upper);
+        let out = run(&upper, "[[[1]]]\nkeep {{G1}}").await.unwrap();
+        assert_eq!(out.text, "[[[1]]]\nKEEP {{G1}}");
+
+        let reverse = MockProvider::new("mock", MockMode::Reverse);
+        assert_eq!(run(&reverse, "abc\nde").await.unwrap().text, "cba\ned");
+
+        let echo = MockProvider::new("mock", MockMode::Echo);
+        let out = run(&echo, "héllo").await.unwrap();
+        assert_eq!(out.text, "héllo");
+        let usage = out.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.total_tokens), (5, 10));
+    }
+
+    #[tokio::test]
+    async fn fixture_misses_are_unsupported() {
+        let fixtures = HashMap::from([("hello".to_string(), "你好".to_string())]);
+        let mock = MockProvider::new("mock", MockMode::Fixture).with_fixtures(fixtures);
+        assert_eq!(run(&mock, " hello ").await.unwrap().text, "你好");
+        let packed = run(&mock, "[[[1]]]\nhello\n[[[2]]]\nhello").await.unwrap();
+        assert_eq!(packed.text, "[[[1]]]\n你好\n[[[2]]]\n你好");
+        assert!(matches!(
+            run(&mock, "bye").await,
+            Err(ProviderError::Unsupported(_))
+        ));
+    }
+}
@@ -1,28 +1,234 @@
+use std::str::FromStr;
+
 use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use serde::Deserialize;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Lang {
     Zh,
     En,
+    Ja,
+    Ko,
+    De,
+    Fr,
+    Es,
+    It,
+    Pt,
+    Ru,
+    Ar,
+    Vi,
+    Th,
 }
 
 impl Lang {
+    pub const ALL: [Lang; 13] = [
+        Lang::Zh,
+        Lang::En,
+        Lang::Ja,
+        Lang::Ko,
+        Lang::De,
+        Lang::Fr,
+        Lang::Es,
+        Lang::It,
+        Lang::Pt,
+        Lang::Ru,
+        Lang::Ar,
+        Lang::Vi,
+        Lang::Th,
+    ];
+
+    /// BCP-47 primary language subtag.
     pub fn as_str(&self) -> &'static str {
         match self {
             Lang::Zh => "zh",
             Lang::En => "en",
+            Lang::Ja => "ja",
+            Lang::Ko => "ko",
+            Lang::De => "de",
+            Lang::Fr => "fr",
+            Lang::Es => "es",
+            Lang::It => "it",
+            Lang::Pt => "pt",
+            Lang::Ru => "ru",
+            Lang::Ar => "ar",
+            Lang::Vi => "vi",
+            Lang::Th => "th",
+        }
+    }
+
+    pub fn english_name(&self) -> &'static str {
+        match self {
+            Lang::Zh => "Chinese",
+            Lang::En => "English",
+            Lang::Ja => "Japanese",
+            Lang::Ko => "Korean",
+            Lang::De => "German",
+            Lang::Fr => "French",
+            Lang::Es => "Spanish",
+            Lang::It => "Italian",
+            Lang::Pt => "Portuguese",
+            Lang::Ru => "Russian",
+            Lang::Ar => "Arabic",
+            Lang::Vi => "Vietnamese",
+            Lang::Th => "Thai",
+        }
+    }
+
+    pub fn native_name(&self) -> &'static str {
+        match self {
+            Lang::Zh => "中文",
+            Lang::En => "English",
+            Lang::Ja => "日本語",
+            Lang::Ko => "한국어",
+            Lang::De => "Deutsch",
+            Lang::Fr => "Français",
+            Lang::Es => "Español",
+            Lang::It => "Italiano",
+            Lang::Pt => "Português",
+            Lang::Ru => "Русский",
+            Lang::Ar => "العربية",
+            Lang::Vi => "Tiếng Việt",
+            Lang::Th => "ไทย",
+        }
+    }
+}
+
+impl FromStr for Lang {
+    type Err = ();
+
+    /// Accepts a BCP-47 tag; only the primary subtag is significant, so
+    /// `zh-CN`, `zh-Hans` and `pt_BR` map to `zh` and `pt`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let primary = s
+            .trim()
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        Lang::ALL
+            .into_iter()
+            .find(|lang| lang.as_str() == primary)
+            .ok_or(())
+    }
+}
+
+/// How the translation should read. Each style selects its own prompt
+/// template; see `prompt::PromptTemplates`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Style {
+    Formal,
+    Casual,
+    Technical,
+    /// Short UI strings: buttons, menus, labels.
+    UiConcise,
+    Literal,
+}
+
+impl Style {
+    pub const ALL: [Style; 5] = [
+        Style::Formal,
+        Style::Casual,
+        Style::Technical,
+        Style::UiConcise,
+        Style::Literal,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Style::Formal => "formal",
+            Style::Casual => "casual",
+            Style::Technical => "technical",
+            Style::UiConcise => "ui-concise",
+            Style::Literal => "literal",
         }
     }
 }
 
+impl FromStr for Style {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Style::ALL
+            .into_iter()
+            .find(|style| style.as_str().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
+
+/// Request options that shape the prompt, beyond text and languages.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TranslateOptions<'a> {
+    /// `None` uses the default template.
+    pub style: Option<Style>,
+    /// Glossary whose terms were masked as `{{Gn}}` placeholders.
+    pub glossary: Option<&'a str>,
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ProviderError {
+    /// Connection-level failure (DNS, refused, reset). Retryable.
     #[error("http: {0}")]
     Http(String),
+    /// No response within the configured timeout. Retryable.
+    #[error("timeout: {0}")]
+    Timeout(String),
+    /// 429 or 5xx from upstream. Retryable.
+    #[error("retryable: status={status} body={body}")]
+    Retryable { status: u16, body: String },
+    /// Any other non-2xx (auth, quota, bad parameters). Not retryable.
+    #[error("rejected: status={status} body={body}")]
+    Rejected { status: u16, body: String },
     #[error("bad_response: {0}")]
     BadResponse(String),
+    #[error("unavailable: {0}")]
+    Unavailable(String),
+    /// The provider cannot handle this request at all (language pair,
+    /// vocabulary). Not retryable.
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+}
+
+impl ProviderError {
+    /// Transient failures worth retrying, or handing to the next provider
+    /// in a fallback chain.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ProviderError::Http(_) | ProviderError::Timeout(_) | ProviderError::Retryable { .. }
+        )
+    }
+}
+
+/// Token counts reported by the upstream for one call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
 }
 
+pub struct Translation {
+    pub text: String,
+    /// `None` when the upstream does not report usage.
+    pub usage: Option<Usage>,
+}
+
+pub enum Chunk {
+    Delta(String),
+    /// Sent at most once, after the last delta.
+    Usage(Usage),
+}
+
+/// Incremental translation output; concatenating the deltas yields the full
+/// translation.
+pub type DeltaStream = BoxStream<'static, Result<Chunk, ProviderError>>;
+
 #[async_trait]
 pub trait TranslateProvider: Send + Sync {
     async fn translate(
@@ -30,10 +236,38 @@ pub trait TranslateProvider: Send + Sync {
         text: &str,
         source: Option<Lang>,
         target: Lang,
-    ) -> Result<String, ProviderError>;
-    fn name(&self) -> &'static str {
-        "deepseek"
+        options: TranslateOptions<'_>,
+    ) -> Result<Translation, ProviderError>;
+    /// Streams the translation as text deltas. Providers without native
+    /// streaming yield the whole translation as a single delta.
+    async fn translate_stream(
+        &self,
+        text: &str,
+        source: Option<Lang>,
+        target: Lang,
+        options: TranslateOptions<'_>,
+    ) -> Result<DeltaStream, ProviderError> {
+        let out = self.translate(text, source, target, options).await?;
+        let chunks = std::iter::once(Chunk::Delta(out.text)).chain(out.usage.map(Chunk::Usage));
+        Ok(Box::pin(futures_util::stream::iter(chunks.map(Ok))))
+    }
+    /// Configured provider name, reported in responses and part of the cache key.
+    fn name(&self) -> &str;
+    /// Upstream model, recorded in the translation memory when known.
+    fn model(&self) -> Option<&str> {
+        None
+    }
+    /// How this provider falls short of a translation model, if it does;
+    /// reported by `/api/capabilities`.
+    fn limitation(&self) -> Option<String> {
+        None
     }
 }
 
+pub mod chain;
 pub mod deepseek;
+pub mod dictionary;
+pub mod mock;
+pub mod openai_compat;
+pub mod prompt;
+pub mod retry;
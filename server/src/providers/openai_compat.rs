@@ -0,0 +1,528 @@
+use std::{pin::Pin, sync::Arc, time::Duration};
+
+use super::{
+    prompt::PromptTemplates,
+    retry::{self, RetryPolicy},
+    Chunk, DeltaStream, Lang, ProviderError, TranslateOptions, TranslateProvider, Translation,
+    Usage,
+};
+use async_trait::async_trait;
+use futures_util::{stream, Stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+pub const DEFAULT_PATH: &str = "/v1/chat/completions";
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client for any OpenAI-compatible chat-completions endpoint (DeepSeek,
+/// vLLM, Ollama, LM Studio, Azure-style gateways, internal proxies).
+pub struct OpenAiCompatProvider {
+    client: reqwest::Client,
+    name: String,
+    url: String,
+    api_key: Option<String>,
+    model: String,
+    timeout: Duration,
+    retry: RetryPolicy,
+    prompts: Arc<PromptTemplates>,
+}
+
+pub struct OpenAiCompatConfig {
+    /// Reported through `TranslateProvider::name` and used in cache keys.
+    pub name: String,
+    pub base_url: String,
+    pub path: String,
+    /// Sent as `Authorization: Bearer`; gateways that authenticate through a
+    /// custom header leave this empty and use `headers` instead.
+    pub api_key: Option<String>,
+    pub model: String,
+    pub headers: HeaderMap,
+    /// Per-attempt timeout for buffered requests; bounds each read when
+    /// streaming.
+    pub timeout: Duration,
+    pub retry: RetryPolicy,
+    pub prompts: Arc<PromptTemplates>,
+}
+
+impl OpenAiCompatConfig {
+    /// Applies `{prefix}_TIMEOUT_SECONDS` and the retry settings that are
+    /// set; see `RetryPolicy::with_env`. Unset ones keep the configured
+    /// value; set ones that are invalid, or a zero timeout, are an error.
+    pub fn with_env_tuning(mut self, prefix: &str) -> anyhow::Result<Self> {
+        let name = format!("{}_TIMEOUT_SECONDS", prefix);
+        if let Ok(raw) = std::env::var(&name) {
+            self.timeout = parse_timeout(&name, &raw)?;
+        }
+        self.retry = self.retry.with_env(prefix)?;
+        Ok(self)
+    }
+}
+
+/// A zero timeout would fail every request at once.
+fn parse_timeout(name: &str, raw: &str) -> anyhow::Result<Duration> {
+    match raw.trim().parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+        _ => Err(anyhow::anyhow!(
+            "{} must be a positive number of seconds, got {:?}",
+            name,
+            raw
+        )),
+    }
+}
+
+impl OpenAiCompatProvider {
+    pub fn new(config: OpenAiCompatConfig) -> Self {
+        // The timeout bounds each read rather than the whole exchange, so a
+        // long streamed answer is not cut off; buffered requests also get a
+        // total timeout in `send_once`.
+        let client = reqwest::Client::builder()
+            .connect_timeout(config.timeout)
+            .read_timeout(config.timeout)
+            .default_headers(config.headers)
+            .build()
+            .expect("reqwest client");
+        let url = format!(
+            "{}/{}",
+            config.base_url.trim_end_matches('/'),
+            config.path.trim_start_matches('/')
+        );
+        Self {
+            client,
+            name: config.name,
+            url,
+            api_key: config.api_key,
+            model: config.model,
+            timeout: config.timeout,
+            retry: config.retry,
+            prompts: config.prompts,
+        }
+    }
+
+    fn chat_request<'a>(
+        &'a self,
+        text: &str,
+        source: Option<Lang>,
+        target: Lang,
+        options: TranslateOptions<'_>,
+        stream: bool,
+    ) -> ChatReq<'a> {
+        let (system, user) = self.prompts.render(text, source, target, options);
+
+        ChatReq {
+            model: &self.model,
+            temperature: 0.2,
+            top_p: 1.0,
+            stream,
+            stream_options: stream.then_some(StreamOptions {
+                include_usage: true,
+            }),
+            messages: vec![
+                Message {
+                    role: "system",
+                    content: system,
+                },
+                Message {
+                    role: "user",
+                    content: user,
+                },
+            ],
+        }
+    }
+
+    /// Sends `body`, retrying retryable failures per the `RetryPolicy`, and
+    /// returns the first successful response.
+    async fn send(&self, body: &ChatReq<'_>) -> Result<reqwest::Response, ProviderError> {
+        let mut attempt = 0;
+        loop {
+            match self.send_once(body).await {
+                Ok(resp) => return Ok(resp),
+                Err((err, retry_after))
+                    if err.is_retryable() && attempt < self.retry.max_retries =>
+                {
+                    let delay = retry_after.unwrap_or_else(|| self.retry.backoff(attempt));
+                    if delay > self.retry.max_delay {
+                        return Err(err);
+                    }
+                    attempt += 1;
+                    warn!(
+                        provider = %self.name,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %err,
+                        "retrying provider request"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err((err, _)) => return Err(err),
+            }
+        }
+    }
+
+    /// One HTTP round trip. On failure also returns the server's
+    /// `Retry-After`, if it sent one.
+    async fn send_once(
+        &self,
+        body: &ChatReq<'_>,
+    ) -> Result<reqwest::Response, (ProviderError, Option<Duration>)> {
+        let mut req = self.client.post(&self.url).json(body);
+        if !body.stream {
+            req = req.timeout(self.timeout);
+        }
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let resp = req.send().await.map_err(|e| (transport_error(e), None))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let retry_after = retry::retry_after(resp.headers());
+            let body = resp.text().await.unwrap_or_default();
+            let err = if status.as_u16() == 429 || status.is_server_error() {
+                ProviderError::Retryable {
+                    status: status.as_u16(),
+                    body,
+                }
+            } else {
+                ProviderError::Rejected {
+                    status: status.as_u16(),
+                    body,
+                }
+            };
+            return Err((err, retry_after));
+        }
+        Ok(resp)
+    }
+}
+
+fn transport_error(e: reqwest::Error) -> ProviderError {
+    if e.is_timeout() {
+        ProviderError::Timeout(e.to_string())
+    } else {
+        ProviderError::Http(e.to_string())
+    }
+}
+
+#[derive(Serialize)]
+struct ChatReq<'a> {
+    model: &'a str,
+    temperature: f32,
+    top_p: f32,
+    stream: bool,
+    /// Asks for a final chunk carrying the token usage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    messages: Vec<Message<'a>>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChatResp {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    delta: ChunkDelta,
+}
+
+#[derive(Deserialize)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[async_trait]
+impl TranslateProvider for OpenAiCompatProvider {
+    async fn translate(
+        &self,
+        text: &str,
+        source: Option<Lang>,
+        target: Lang,
+        options: TranslateOptions<'_>,
+    ) -> Result<Translation, ProviderError> {
+        let body = self.chat_request(text, source, target, options, false);
+        let resp = self.send(&body).await?;
+        let out: ChatResp = resp
+            .json()
+            .await
+            .map_err(|e| ProviderError::BadResponse(e.to_string()))?;
+        let text = out
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content)
+            .ok_or_else(|| ProviderError::BadResponse("empty choices".into()))?;
+        Ok(Translation {
+            text,
+            usage: out.usage,
+        })
+    }
+
+    async fn translate_stream(
+        &self,
+        text: &str,
+        source: Option<Lang>,
+        target: Lang,
+        options: TranslateOptions<'_>,
+    ) -> Result<DeltaStream, ProviderError> {
+        let body = self.chat_request(text, source, target, options, true);
+        let resp = self.send(&body).await?;
+        Ok(Box::pin(sse_deltas(resp.bytes_stream())))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+}
+
+/// Turns an OpenAI-style `text/event-stream` body into content deltas and
+/// the final usage. Ends at `data: [DONE]` or when the upstream closes the
+/// connection.
+fn sse_deltas<S, B>(bytes: S) -> impl Stream<Item = Result<Chunk, ProviderError>> + Send
+where
+    S: Stream<Item = reqwest::Result<B>> + Send + 'static,
+    B: AsRef<[u8]>,
+{
+    struct State<S> {
+        bytes: Pin<Box<S>>,
+        buf: Vec<u8>,
+        /// Usage arrives with or after the last delta; held back so it is
+        /// emitted last.
+        usage: Option<Usage>,
+        finished: bool,
+    }
+
+    let state = State {
+        bytes: Box::pin(bytes),
+        buf: Vec::new(),
+        usage: None,
+        finished: false,
+    };
+    stream::unfold(state, |mut st| async move {
+        loop {
+            if st.finished {
+                return st.usage.take().map(|usage| (Ok(Chunk::Usage(usage)), st));
+            }
+            if let Some(pos) = st.buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = st.buf.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    st.finished = true;
+                    continue;
+                }
+                match serde_json::from_str::<ChatChunk>(data) {
+                    Ok(chunk) => {
+                        if chunk.usage.is_some() {
+                            st.usage = chunk.usage;
+                        }
+                        let content = chunk
+                            .choices
+                            .into_iter()
+                            .next()
+                            .and_then(|c| c.delta.content)
+                            .filter(|c| !c.is_empty());
+                        if let Some(content) = content {
+                            return Some((Ok(Chunk::Delta(content)), st));
+                        }
+                    }
+                    Err(e) => {
+                        st.finished = true;
+                        st.usage = None;
+                        return Some((Err(ProviderError::BadResponse(e.to_string())), st));
+                    }
+                }
+                continue;
+            }
+            match st.bytes.next().await {
+                Some(Ok(chunk)) => st.buf.extend_from_slice(chunk.as_ref()),
+                Some(Err(e)) => {
+                    st.finished = true;
+                    st.usage = None;
+                    return Some((Err(transport_error(e)), st));
+                }
+                None => st.finished = true,
+            }
+        }
+    })
+}
+
+/// `TRANSLATE_PROVIDER=openai_compat`. `None` without
+/// `OPENAI_COMPAT_BASE_URL`; errors for settings that are present but wrong.
+pub fn from_env(prompts: Arc<PromptTemplates>) -> anyhow::Result<Option<OpenAiCompatProvider>> {
+    let Ok(base_url) = std::env::var("OPENAI_COMPAT_BASE_URL") else {
+        return Ok(None);
+    };
+    let model = std::env::var("OPENAI_COMPAT_MODEL")
+        .map_err(|_| anyhow::anyhow!("missing OPENAI_COMPAT_MODEL"))?;
+    let name = std::env::var("OPENAI_COMPAT_NAME").unwrap_or_else(|_| "openai_compat".to_string());
+    let path = std::env::var("OPENAI_COMPAT_PATH").unwrap_or_else(|_| DEFAULT_PATH.to_string());
+    let api_key = std::env::var("OPENAI_COMPAT_API_KEY")
+        .ok()
+        .filter(|v| !v.is_empty());
+    let headers = match std::env::var("OPENAI_COMPAT_HEADERS") {
+        Ok(raw) => parse_headers(&raw)?,
+        Err(_) => HeaderMap::new(),
+    };
+    Ok(Some(OpenAiCompatProvider::new(
+        OpenAiCompatConfig {
+            name,
+            base_url,
+            path,
+            api_key,
+            model,
+            headers,
+            timeout: DEFAULT_TIMEOUT,
+            retry: RetryPolicy::default(),
+            prompts,
+        }
+        .with_env_tuning("OPENAI_COMPAT")?,
+    )))
+}
+
+/// Parses `Name: value; Other-Name: value` into a header map.
+fn parse_headers(raw: &str) -> anyhow::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for pair in raw.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+        let (name, value) = pair
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("invalid header (expected `Name: value`): {}", pair))?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|e| anyhow::anyhow!("invalid header name {:?}: {}", name, e))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|e| anyhow::anyhow!("invalid header value for {}: {}", name, e))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_header_pairs() {
+        let headers = parse_headers("X-Team: platform; x-url: http://a:8080/b ;;").unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["x-team"], "platform");
+        assert_eq!(headers["X-Url"], "http://a:8080/b");
+        assert!(parse_headers("  ").unwrap().is_empty());
+
+        for (raw, expected) in [
+            ("X-Team=platform", "invalid header (expected `Name: value`)"),
+            ("X-Ok: 1; X-Bad", "invalid header (expected `Name: value`)"),
+            ("Bad Name: v", "invalid header name"),
+            (": v", "invalid header name"),
+            ("X-Team: a\u{7f}b", "invalid header value for x-team"),
+        ] {
+            let err = parse_headers(raw).unwrap_err().to_string();
+            assert!(err.starts_with(expected), "{raw:?}: {err}");
+        }
+    }
+
+    /// One test for every case, since they share the process environment.
+    #[test]
+    fn configures_from_env() {
+        const VARS: [&str; 7] = [
+            "OPENAI_COMPAT_BASE_URL",
+            "OPENAI_COMPAT_MODEL",
+            "OPENAI_COMPAT_NAME",
+            "OPENAI_COMPAT_PATH",
+            "OPENAI_COMPAT_HEADERS",
+            "OPENAI_COMPAT_TIMEOUT_SECONDS",
+            "OPENAI_COMPAT_MAX_RETRIES",
+        ];
+        let prompts = Arc::new(PromptTemplates::default());
+        let from_env = || from_env(prompts.clone()).map_err(|e| e.to_string());
+        let error = || from_env().err().unwrap_or_default();
+        let clear = || {
+            for var in VARS {
+                std::env::remove_var(var);
+            }
+        };
+        clear();
+
+        assert!(matches!(from_env(), Ok(None)));
+
+        std::env::set_var("OPENAI_COMPAT_BASE_URL", "http://gateway.internal/");
+        assert_eq!(error(), "missing OPENAI_COMPAT_MODEL");
+
+        std::env::set_var("OPENAI_COMPAT_MODEL", "qwen2.5:7b");
+        let provider = from_env().unwrap().unwrap();
+        assert_eq!(provider.name(), "openai_compat");
+        assert_eq!(provider.url, "http://gateway.internal/v1/chat/completions");
+        assert_eq!(provider.timeout, DEFAULT_TIMEOUT);
+
+        std::env::set_var("OPENAI_COMPAT_NAME", "gateway");
+        std::env::set_var("OPENAI_COMPAT_PATH", "/chat");
+        std::env::set_var("OPENAI_COMPAT_HEADERS", "X-Team: platform");
+        std::env::set_var("OPENAI_COMPAT_TIMEOUT_SECONDS", "30");
+        std::env::set_var("OPENAI_COMPAT_MAX_RETRIES", "1");
+        let provider = from_env().unwrap().unwrap();
+        assert_eq!(provider.name(), "gateway");
+        assert_eq!(provider.model(), Some("qwen2.5:7b"));
+        assert_eq!(provider.url, "http://gateway.internal/chat");
+        assert_eq!(provider.timeout, Duration::from_secs(30));
+        assert_eq!(provider.retry.max_retries, 1);
+
+        std::env::set_var("OPENAI_COMPAT_HEADERS", "X-Team platform");
+        assert!(error().starts_with("invalid header"));
+        std::env::set_var("OPENAI_COMPAT_HEADERS", "X-Team: platform");
+
+        std::env::set_var("OPENAI_COMPAT_TIMEOUT_SECONDS", "0");
+        assert!(error().starts_with("OPENAI_COMPAT_TIMEOUT_SECONDS"));
+
+        clear();
+    }
+
+    #[test]
+    fn timeout_must_be_positive() {
+        let name = "X_TIMEOUT_SECONDS";
+        assert_eq!(
+            parse_timeout(name, " 30 ").unwrap(),
+            Duration::from_secs(30)
+        );
+        for bad in ["0", "-5", "soon"] {
+            let err = parse_timeout(name, bad).unwrap_err();
+            assert!(err.to_string().starts_with(name), "{}", err);
+        }
+    }
+}
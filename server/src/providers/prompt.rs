@@ -0,0 +1,158 @@
+//! Prompt templates for chat-model providers. Built-in templates cover the
+//! default and every `Style`; any of them can be replaced by a file in
+//! `PROMPT_DIR`.
+//!
+//! Templates may use `{source}`, `{target}` (language codes, `auto` when the
+//! source is unknown), `{source_name}`, `{target_name}` (English names),
+//! `{style}` (empty for the default), `{glossary_name}`, `{glossary}` (a note
+//! about the masked glossary terms, empty without a glossary) and, in the
+//! user template only, `{text}`. Other braces are left as they are.
+
+use std::{collections::HashMap, path::Path};
+
+use super::{Lang, Style, TranslateOptions};
+
+const BASE: &str = "You are a translation engine. Preserve formatting, code blocks and placeholders. Only output the translated text.";
+const DEFAULT_USER: &str = "Source: {source}\nTarget: {target}\nText:\n{text}";
+
+pub struct PromptTemplates {
+    system: String,
+    styles: HashMap<Style, String>,
+    user: String,
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        Self {
+            system: format!("{}\n{{glossary}}", BASE),
+            styles: Style::ALL
+                .into_iter()
+                .map(|style| {
+                    (
+                        style,
+                        format!("{} {}\n{{glossary}}", BASE, instruction(style)),
+                    )
+                })
+                .collect(),
+            user: DEFAULT_USER.to_string(),
+        }
+    }
+}
+
+fn instruction(style: Style) -> &'static str {
+    match style {
+        Style::Formal => "Use a formal, polite register.",
+        Style::Casual => "Use a casual, conversational tone.",
+        Style::Technical => {
+            "Use precise technical terminology; keep identifiers, commands, units and product names unchanged."
+        }
+        Style::UiConcise => {
+            "The text is a user interface string: keep it as short as the source or shorter, and use the wording common in software of the target language."
+        }
+        Style::Literal => {
+            "Translate as literally as the target language allows; do not rephrase, add or omit anything."
+        }
+    }
+}
+
+impl PromptTemplates {
+    /// Starts from the built-in templates and replaces those with a file in
+    /// `dir`: `system.txt` (default), `<style>.txt` (e.g. `ui-concise.txt`)
+    /// and `user.txt`, which must contain `{text}`.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let mut templates = Self::default();
+        let read = |name: &str| -> anyhow::Result<Option<String>> {
+            let path = dir.join(format!("{}.txt", name));
+            match std::fs::read_to_string(&path) {
+                Ok(raw) => Ok(Some(raw.trim().to_string())),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(anyhow::anyhow!("read {}: {}", path.display(), err)),
+            }
+        };
+        if let Some(system) = read("system")? {
+            templates.system = system;
+        }
+        for style in Style::ALL {
+            if let Some(system) = read(style.as_str())? {
+                templates.styles.insert(style, system);
+            }
+        }
+        if let Some(user) = read("user")? {
+            if !user.contains("{text}") {
+                return Err(anyhow::anyhow!(
+                    "{}: user template has no {{text}} variable",
+                    dir.join("user.txt").display()
+                ));
+            }
+            templates.user = user;
+        }
+        Ok(templates)
+    }
+
+    /// Returns the `(system, user)` messages for one request.
+    pub fn render(
+        &self,
+        text: &str,
+        source: Option<Lang>,
+        target: Lang,
+        options: TranslateOptions<'_>,
+    ) -> (String, String) {
+        let glossary_note = options
+            .glossary
+            .map(|name| {
+                format!(
+                    "Placeholders such as {{{{G1}}}} stand for terms of the \"{}\" glossary; copy them unchanged.",
+                    name
+                )
+            })
+            .unwrap_or_default();
+        let vars = |name: &str| -> Option<&str> {
+            Some(match name {
+                "source" => source.map_or("auto", |l| l.as_str()),
+                "target" => target.as_str(),
+                "source_name" => source.map_or("the detected language", |l| l.english_name()),
+                "target_name" => target.english_name(),
+                "style" => options.style.map_or("", |s| s.as_str()),
+                "glossary_name" => options.glossary.unwrap_or_default(),
+                "glossary" => &glossary_note,
+                _ => return None,
+            })
+        };
+        let system = match options.style {
+            Some(style) => &self.styles[&style],
+            None => &self.system,
+        };
+        let system = substitute(system, &vars).trim().to_string();
+        let user = substitute(&self.user, &|name| match name {
+            "text" => Some(text),
+            other => vars(other),
+        });
+        (system, user)
+    }
+}
+
+/// Replaces each `{name}` that `vars` knows in a single pass, so values are
+/// never themselves expanded.
+fn substitute<'a>(template: &str, vars: &dyn Fn(&str) -> Option<&'a str>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after
+            .find('}')
+            .and_then(|close| vars(&after[..close]).map(|value| (value, close)));
+        match value {
+            Some((value, close)) => {
+                out.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
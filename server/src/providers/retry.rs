@@ -0,0 +1,134 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+
+/// Jittered exponential backoff for retryable provider failures.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; `0` disables retrying.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    /// Upper bound for a single wait. A `Retry-After` longer than this is
+    /// not waited out; the error is returned so a fallback can take over.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Overrides the fields whose `{prefix}_MAX_RETRIES`,
+    /// `{prefix}_RETRY_BASE_MS` or `{prefix}_RETRY_MAX_MS` variable is set;
+    /// the others keep their current value. Values that are set but not a
+    /// valid count are an error.
+    pub fn with_env(self, prefix: &str) -> anyhow::Result<Self> {
+        self.with_overrides(prefix, |name| std::env::var(name).ok())
+    }
+
+    fn with_overrides(
+        mut self,
+        prefix: &str,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Self> {
+        let number = |suffix: &str| -> anyhow::Result<Option<u64>> {
+            let name = format!("{}_{}", prefix, suffix);
+            var(&name)
+                .map(|v| {
+                    v.trim().parse::<u64>().map_err(|_| {
+                        anyhow::anyhow!("{} must be a non-negative integer, got {:?}", name, v)
+                    })
+                })
+                .transpose()
+        };
+        if let Some(max_retries) = number("MAX_RETRIES")? {
+            self.max_retries = u32::try_from(max_retries).map_err(|_| {
+                anyhow::anyhow!("{}_MAX_RETRIES is too large: {}", prefix, max_retries)
+            })?;
+        }
+        if let Some(ms) = number("RETRY_BASE_MS")? {
+            self.base_delay = Duration::from_millis(ms);
+        }
+        if let Some(ms) = number("RETRY_MAX_MS")? {
+            self.max_delay = Duration::from_millis(ms);
+        }
+        Ok(self)
+    }
+
+    /// "Full jitter": a uniform wait in `[0, min(max, base * 2^attempt)]`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let millis = ceiling.as_millis() as u64;
+        if millis == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(random_u64() % (millis + 1))
+    }
+}
+
+/// `Retry-After` as either delta-seconds or an HTTP-date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    let delta = at - OffsetDateTime::now_utc();
+    Some(delta.try_into().unwrap_or(Duration::ZERO))
+}
+
+/// Randomness for jitter without pulling in a RNG crate: every
+/// `RandomState` is seeded freshly by the standard library.
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_only_the_settings_given() {
+        let configured = RetryPolicy {
+            max_retries: 7,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(1),
+        };
+        let tuned = configured
+            .clone()
+            .with_overrides("T", |name| {
+                (name == "T_RETRY_MAX_MS").then(|| "9000".into())
+            })
+            .unwrap();
+        assert_eq!(tuned.max_retries, 7);
+        assert_eq!(tuned.base_delay, Duration::from_millis(50));
+        assert_eq!(tuned.max_delay, Duration::from_secs(9));
+
+        let untouched = RetryPolicy::default()
+            .with_env("RETRY_TEST_UNSET_PREFIX")
+            .unwrap();
+        assert_eq!(untouched.max_retries, RetryPolicy::default().max_retries);
+
+        for bad in ["4294967296", "-1", "three"] {
+            let err = configured
+                .clone()
+                .with_overrides("T", |name| (name == "T_MAX_RETRIES").then(|| bad.into()))
+                .unwrap_err();
+            assert!(err.to_string().starts_with("T_MAX_RETRIES"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Operator endpoints under `/api/admin`, guarded by `ADMIN_TOKEN`: cache
+//! inspection and invalidation, token usage. Invalidation also drops the
+//! matching translation memory entries, which would otherwise keep serving
+//! the removed translation.
+
+use std::str::FromStr;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::{header, request::Parts},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use super::tools::{cache_key, cache_prefix, CacheScope, Format};
+use crate::{
+    app::AppState,
+    cache::CacheStats,
+    error::{ApiError, ApiResult},
+    providers::{Lang, Style},
+    usage::Snapshot,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/cache", get(cache_stats))
+        .route("/api/admin/cache/lookup", post(cache_lookup))
+        .route("/api/admin/cache/delete", post(cache_delete))
+        .route("/api/admin/cache/flush", post(cache_flush))
+        .route("/api/admin/usage", get(usage))
+}
+
+/// Accepts `Authorization: Bearer <token>` or `X-Admin-Token: <token>`.
+/// Also guards operator endpoints elsewhere, such as the translation memory
+/// import.
+pub(super) struct Admin;
+
+#[async_trait]
+impl FromRequestParts<AppState> for Admin {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, ApiError> {
+        let Some(expected) = state.admin_token.as_deref() else {
+            return Err(ApiError::Unauthorized(
+                "admin API is disabled (ADMIN_TOKEN is not set)".into(),
+            ));
+        };
+        let header_value = |name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+        };
+        let given = header_value(header::AUTHORIZATION)
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| header_value(header::HeaderName::from_static("x-admin-token")));
+        match given {
+            Some(token) if constant_time_eq(token.trim().as_bytes(), expected.as_bytes()) => {
+                Ok(Admin)
+            }
+            Some(_) => Err(ApiError::Unauthorized("invalid admin token".into())),
+            None => Err(ApiError::Unauthorized("missing admin token".into())),
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsResp {
+    backend: &'static str,
+    /// `None` before the first lookup.
+    hit_ratio: Option<f64>,
+    #[serde(flatten)]
+    stats: CacheStats,
+}
+
+/// Identifies one cache entry by what `cache_key` hashes.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryReq {
+    /// Defaults to every provider in the chain.
+    #[serde(default)]
+    provider: Option<String>,
+    source_lang: String,
+    target_lang: String,
+    text: String,
+    #[serde(default)]
+    glossary: Option<String>,
+    #[serde(default)]
+    format: Format,
+    #[serde(default)]
+    style: Option<Style>,
+}
+
+#[derive(Serialize)]
+struct LookupResp {
+    entries: Vec<EntryInfo>,
+}
+
+#[derive(Serialize)]
+struct EntryInfo {
+    provider: String,
+    key: String,
+    translation: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RemovedResp {
+    /// Cache entries.
+    removed: u64,
+    /// Translation memory entries; 0 when it is disabled.
+    memory_removed: u64,
+}
+
+#[derive(Deserialize)]
+struct FlushReq {
+    provider: String,
+}
+
+/// `GET /api/admin/cache`
+async fn cache_stats(_: Admin, State(state): State<AppState>) -> ApiResult<StatsResp> {
+    let stats = state.cache.stats().await;
+    let lookups = stats.hits + stats.misses;
+    Ok(Json(StatsResp {
+        backend: state.cache.name(),
+        hit_ratio: (lookups > 0).then(|| stats.hits as f64 / lookups as f64),
+        stats,
+    }))
+}
+
+/// `POST /api/admin/cache/lookup`
+async fn cache_lookup(
+    _: Admin,
+    State(state): State<AppState>,
+    Json(req): Json<EntryReq>,
+) -> ApiResult<LookupResp> {
+    let mut entries = Vec::new();
+    let entry = Entry::parse(&req)?;
+    for (provider, key) in entry_keys(&state, &req, &entry) {
+        if let Some(translation) = state.cache.get(&key).await {
+            entries.push(EntryInfo {
+                provider,
+                key,
+                translation,
+            });
+        }
+    }
+    Ok(Json(LookupResp { entries }))
+}
+
+/// `POST /api/admin/cache/delete`
+async fn cache_delete(
+    _: Admin,
+    State(state): State<AppState>,
+    Json(req): Json<EntryReq>,
+) -> ApiResult<RemovedResp> {
+    let entry = Entry::parse(&req)?;
+    let mut removed = 0;
+    for (_, key) in entry_keys(&state, &req, &entry) {
+        if state.cache.remove(&key).await {
+            removed += 1;
+        }
+    }
+    // Only plain-text requests are remembered.
+    let memory_removed = match &state.memory {
+        Some(memory) if entry.scope.is_plain() => memory
+            .remove(
+                entry.source,
+                entry.target,
+                entry.text,
+                requested_provider(&req),
+            )
+            .await
+            .map_err(memory_failure)?,
+        _ => 0,
+    };
+    info!(removed, memory_removed, "cache entries deleted");
+    Ok(Json(RemovedResp {
+        removed,
+        memory_removed,
+    }))
+}
+
+/// `POST /api/admin/cache/flush`: drops every entry of one provider.
+async fn cache_flush(
+    _: Admin,
+    State(state): State<AppState>,
+    Json(req): Json<FlushReq>,
+) -> ApiResult<RemovedResp> {
+    let provider = req.provider.trim();
+    if provider.is_empty() {
+        return Err(ApiError::BadRequest("empty provider".into()));
+    }
+    let removed = state.cache.remove_prefix(&cache_prefix(provider)).await;
+    let memory_removed = match &state.memory {
+        Some(memory) => memory
+            .remove_provider(provider)
+            .await
+            .map_err(memory_failure)?,
+        None => 0,
+    };
+    info!(provider, removed, memory_removed, "cache flushed");
+    Ok(Json(RemovedResp {
+        removed,
+        memory_removed,
+    }))
+}
+
+/// `GET /api/admin/usage`: token totals since start-up, per provider/model
+/// and per API key, plus today's figures against the budgets.
+async fn usage(_: Admin, State(state): State<AppState>) -> ApiResult<Snapshot> {
+    Ok(Json(state.usage.snapshot()))
+}
+
+/// An `EntryReq` with its languages parsed and its text normalised the way
+/// the translate handler does.
+struct Entry<'a> {
+    source: Lang,
+    target: Lang,
+    text: &'a str,
+    scope: CacheScope<'a>,
+}
+
+impl<'a> Entry<'a> {
+    fn parse(req: &'a EntryReq) -> Result<Self, ApiError> {
+        Ok(Self {
+            source: parse_lang(&req.source_lang)?,
+            target: parse_lang(&req.target_lang)?,
+            text: match req.format {
+                Format::Text => req.text.trim(),
+                Format::Markdown => req.text.as_str(),
+            },
+            scope: CacheScope {
+                glossary: req
+                    .glossary
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|g| !g.is_empty()),
+                format: req.format,
+                style: req.style,
+            },
+        })
+    }
+}
+
+fn requested_provider(req: &EntryReq) -> Option<&str> {
+    req.provider
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+}
+
+/// `(provider, key)` for the requested provider, or every provider in the
+/// chain.
+fn entry_keys(state: &AppState, req: &EntryReq, entry: &Entry) -> Vec<(String, String)> {
+    let providers: Vec<String> = match requested_provider(req) {
+        Some(provider) => vec![provider.to_string()],
+        None => state
+            .providers
+            .iter()
+            .flat_map(|chain| chain.names())
+            .map(str::to_string)
+            .collect(),
+    };
+    providers
+        .into_iter()
+        .map(|provider| {
+            let key = cache_key(
+                &provider,
+                entry.source.as_str(),
+                entry.target.as_str(),
+                entry.scope,
+                entry.text,
+            );
+            (provider, key)
+        })
+        .collect()
+}
+
+fn memory_failure(err: anyhow::Error) -> ApiError {
+    error!(error = %err, "translation memory failed");
+    ApiError::Internal("translation memory failed".into())
+}
+
+fn parse_lang(value: &str) -> Result<Lang, ApiError> {
+    Lang::from_str(value)
+        .map_err(|_| ApiError::BadRequest(format!("unsupported language: {}", value)))
+}
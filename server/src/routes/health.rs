@@ -1,4 +1,4 @@
-use axum::{routing::get, Json, Router};
+use axum::{extract::State, routing::get, Json, Router};
 use serde::Serialize;
 
 use crate::app::AppState;
@@ -6,12 +6,114 @@ use crate::app::AppState;
 #[derive(Serialize)]
 struct HealthResp {
     ok: bool,
+    /// Names of the features switched off by configuration.
+    disabled: Vec<&'static str>,
 }
 
-async fn handler() -> Json<HealthResp> {
-    Json(HealthResp { ok: true })
+#[derive(Serialize)]
+struct CapabilitiesResp {
+    features: Vec<Feature>,
+    /// Configured translate providers in fallback order.
+    providers: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Feature {
+    name: &'static str,
+    enabled: bool,
+    /// Why the feature is disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// Why an enabled feature works only in part.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    degraded: Option<String>,
+}
+
+impl Feature {
+    fn on(name: &'static str) -> Self {
+        Self {
+            name,
+            enabled: true,
+            reason: None,
+            degraded: None,
+        }
+    }
+
+    fn unless(name: &'static str, disabled: Option<String>) -> Self {
+        Self {
+            name,
+            enabled: disabled.is_none(),
+            reason: disabled,
+            degraded: None,
+        }
+    }
+
+    fn degraded(mut self, degraded: Option<String>) -> Self {
+        self.degraded = degraded;
+        self
+    }
+}
+
+/// Every feature with its state. The stateless tools are always on;
+/// translation needs a provider, and is degraded when only the phrase
+/// dictionary backs it; the translation memory needs `TM_PATH` and the
+/// admin API `ADMIN_TOKEN`.
+fn features(state: &AppState) -> Vec<Feature> {
+    vec![
+        Feature::unless(
+            "translate",
+            state.providers.as_ref().err().map(|r| r.to_string()),
+        )
+        .degraded(state.providers.as_ref().ok().and_then(|c| c.degraded())),
+        Feature::unless(
+            "translationMemory",
+            state
+                .memory
+                .is_none()
+                .then(|| "TM_PATH is empty".to_string()),
+        ),
+        Feature::unless(
+            "admin",
+            state
+                .admin_token
+                .is_none()
+                .then(|| "ADMIN_TOKEN is not set".to_string()),
+        ),
+        Feature::on("detect"),
+        Feature::on("jsonFormat"),
+        Feature::on("jsonQuery"),
+        Feature::on("jsonSchema"),
+        Feature::on("jsonDiff"),
+        Feature::on("hash"),
+        Feature::on("base64"),
+        Feature::on("timestamp"),
+    ]
+}
+
+async fn handler(State(state): State<AppState>) -> Json<HealthResp> {
+    let disabled = features(&state)
+        .into_iter()
+        .filter(|f| !f.enabled)
+        .map(|f| f.name)
+        .collect();
+    Json(HealthResp { ok: true, disabled })
+}
+
+async fn capabilities(State(state): State<AppState>) -> Json<CapabilitiesResp> {
+    let providers = state
+        .providers
+        .iter()
+        .flat_map(|chain| chain.names())
+        .map(str::to_string)
+        .collect();
+    Json(CapabilitiesResp {
+        features: features(&state),
+        providers,
+    })
 }
 
 pub fn router() -> Router<AppState> {
-    Router::new().route("/api/health", get(handler))
+    Router::new()
+        .route("/api/health", get(handler))
+        .route("/api/capabilities", get(capabilities))
 }
@@ -8,6 +8,7 @@ use tower_http::{
 
 use crate::{app::AppState, error::ApiError};
 
+mod admin;
 mod health;
 pub mod tools;
 
@@ -20,7 +21,8 @@ pub fn router(state: AppState) -> Router {
 
     let api = Router::new()
         .merge(health::router())
-        .merge(tools::router())
+        .merge(admin::router())
+        .merge(tools::router(state.jobs.max_bytes))
         .fallback(api_not_found);
 
     let static_dir_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../web/public");
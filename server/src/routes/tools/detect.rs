@@ -0,0 +1,132 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    detect::{self, Detection},
+    error::{ApiError, ApiResult},
+};
+
+const MAX_BATCH: usize = 1000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct DetectReq {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    texts: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct DetectResp {
+    results: Vec<DetectItem>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DetectItem {
+    lang: Option<&'static str>,
+    script: Option<&'static str>,
+    confidence: f32,
+    candidates: Vec<CandidateItem>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CandidateItem {
+    lang: &'static str,
+    confidence: f32,
+}
+
+pub async fn handle(Json(req): Json<DetectReq>) -> ApiResult<DetectResp> {
+    let texts = match (req.text, req.texts) {
+        (Some(text), None) => vec![text],
+        (None, Some(texts)) => texts,
+        (Some(_), Some(_)) => {
+            return Err(ApiError::BadRequest(
+                "provide either text or texts, not both".into(),
+            ))
+        }
+        (None, None) => return Err(ApiError::BadRequest("missing text".into())),
+    };
+    if texts.is_empty() {
+        return Err(ApiError::BadRequest("empty texts".into()));
+    }
+    if texts.len() > MAX_BATCH {
+        return Err(ApiError::BadRequest(format!(
+            "too many texts: {} (max {})",
+            texts.len(),
+            MAX_BATCH
+        )));
+    }
+
+    let results = texts
+        .iter()
+        .map(|text| to_item(detect::detect(text)))
+        .collect();
+    Ok(Json(DetectResp { results }))
+}
+
+fn to_item(detection: Detection) -> DetectItem {
+    let top = detection.top();
+    DetectItem {
+        lang: top.map(|c| c.lang.as_str()),
+        script: detection.script.map(|s| s.as_str()),
+        confidence: top.map(|c| c.confidence).unwrap_or_default(),
+        candidates: detection
+            .candidates
+            .iter()
+            .map(|c| CandidateItem {
+                lang: c.lang.as_str(),
+                confidence: c.confidence,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    async fn detect(req: Value) -> ApiResult<DetectResp> {
+        handle(Json(serde_json::from_value(req).unwrap())).await
+    }
+
+    #[tokio::test]
+    async fn single_and_batch() {
+        let Json(resp) = detect(json!({ "text": "こんにちは" })).await.unwrap();
+        let item = &resp.results[0];
+        assert_eq!((item.lang, item.script), (Some("ja"), Some("Kana")));
+
+        let Json(resp) = detect(json!({ "texts": ["你好", "Привет", "42"] }))
+            .await
+            .unwrap();
+        let got: Vec<_> = resp.results.iter().map(|i| (i.lang, i.script)).collect();
+        assert_eq!(
+            got,
+            [
+                (Some("zh"), Some("Han")),
+                (Some("ru"), Some("Cyrillic")),
+                (None, None)
+            ]
+        );
+        assert_eq!(resp.results[2].confidence, 0.0);
+    }
+
+    #[tokio::test]
+    async fn rejects_ambiguous_or_empty_requests() {
+        for req in [
+            json!({}),
+            json!({ "texts": [] }),
+            json!({ "text": "a", "texts": ["b"] }),
+        ] {
+            assert!(
+                matches!(detect(req.clone()).await, Err(ApiError::BadRequest(_))),
+                "{req}"
+            );
+        }
+    }
+}
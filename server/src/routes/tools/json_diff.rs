@@ -0,0 +1,493 @@
+//! Structural JSON diff, emitted as a change list, an RFC 6902 JSON Patch
+//! and an RFC 7386 merge patch, plus applying either kind of patch.
+
+use std::collections::HashMap;
+
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::json_format::parse;
+use crate::error::{ApiError, ApiResult};
+
+/// Above this many element comparisons, ordered arrays are aligned by
+/// position instead of by longest common subsequence.
+const LCS_MAX_CELLS: usize = 1_000_000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct JsonDiffReq {
+    /// The original document, as JSON text.
+    left: String,
+    /// The new document, as JSON text.
+    right: String,
+    /// Compare arrays as multisets: order is ignored and only elements
+    /// without an equal counterpart are reported.
+    #[serde(default)]
+    arrays_as_sets: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct JsonDiffResp {
+    equal: bool,
+    changes: Vec<Change>,
+    /// RFC 6902 operations turning `left` into `right`.
+    patch: Vec<Value>,
+    /// RFC 7386 merge patch; it cannot express `null` values inside objects
+    /// and replaces changed arrays as a whole.
+    merge_patch: Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Change {
+    kind: ChangeKind,
+    /// JSON Pointer; into `left` for removals, into `right` otherwise.
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_value: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<Value>,
+}
+
+#[derive(Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum PatchFormat {
+    /// JSON Patch for an array, merge patch otherwise.
+    #[default]
+    Auto,
+    JsonPatch,
+    MergePatch,
+}
+
+#[derive(Deserialize)]
+pub(super) struct JsonPatchReq {
+    /// The document to patch, as JSON text.
+    json: String,
+    /// The patch, as JSON text.
+    patch: String,
+    #[serde(default)]
+    format: PatchFormat,
+}
+
+#[derive(Serialize)]
+pub(super) struct JsonPatchResp {
+    result: Value,
+}
+
+pub async fn handle_diff(Json(req): Json<JsonDiffReq>) -> ApiResult<JsonDiffResp> {
+    let left = parse(&req.left, "left")?;
+    let right = parse(&req.right, "right")?;
+
+    let mut diff = Diff {
+        sets: req.arrays_as_sets,
+        changes: Vec::new(),
+        patch: Vec::new(),
+    };
+    diff.value(&left, &right, "", "");
+    Ok(Json(JsonDiffResp {
+        equal: diff.changes.is_empty(),
+        merge_patch: merge_patch(&left, &right),
+        changes: diff.changes,
+        patch: diff.patch,
+    }))
+}
+
+pub async fn handle_patch(Json(req): Json<JsonPatchReq>) -> ApiResult<JsonPatchResp> {
+    let mut document = parse(&req.json, "json")?;
+    let patch = parse(&req.patch, "patch")?;
+    let json_patch = match req.format {
+        PatchFormat::Auto => patch.is_array(),
+        PatchFormat::JsonPatch => true,
+        PatchFormat::MergePatch => false,
+    };
+
+    if json_patch {
+        let ops: json_patch::Patch = serde_json::from_value(patch)
+            .map_err(|e| ApiError::BadRequest(format!("invalid json patch: {}", e)))?;
+        json_patch::patch(&mut document, &ops)
+            .map_err(|e| ApiError::BadRequest(format!("patch failed: {}", e)))?;
+    } else {
+        json_patch::merge(&mut document, &patch);
+    }
+    Ok(Json(JsonPatchResp { result: document }))
+}
+
+struct Diff {
+    sets: bool,
+    changes: Vec<Change>,
+    patch: Vec<Value>,
+}
+
+/// How two arrays line up, in order of the right-hand array.
+enum Step {
+    Same,
+    /// Left and right element at the same place, compared recursively.
+    Pair(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+impl Diff {
+    /// `left_path` locates `left` in the original document, `right_path`
+    /// both `right` in the new one and the value being patched: operations
+    /// earlier in the patch have already made the two agree up to here.
+    fn value(&mut self, left: &Value, right: &Value, left_path: &str, right_path: &str) {
+        match (left, right) {
+            (Value::Object(l), Value::Object(r)) => {
+                for (key, lv) in l {
+                    let lp = pointer(left_path, key);
+                    let rp = pointer(right_path, key);
+                    match r.get(key) {
+                        Some(rv) => self.value(lv, rv, &lp, &rp),
+                        None => self.removed(&lp, &rp, lv),
+                    }
+                }
+                for (key, rv) in r {
+                    if !l.contains_key(key) {
+                        self.added(&pointer(right_path, key), rv);
+                    }
+                }
+            }
+            (Value::Array(l), Value::Array(r)) if self.sets => {
+                self.array_as_set(l, r, left_path, right_path)
+            }
+            (Value::Array(l), Value::Array(r)) => self.array(l, r, left_path, right_path),
+            (l, r) if self.same(l, r) => {}
+            (l, r) => {
+                self.changes.push(Change {
+                    kind: ChangeKind::Changed,
+                    path: right_path.to_string(),
+                    old_value: Some(l.clone()),
+                    value: Some(r.clone()),
+                });
+                self.patch
+                    .push(json!({ "op": "replace", "path": right_path, "value": r }));
+            }
+        }
+    }
+
+    fn array(&mut self, l: &[Value], r: &[Value], left_path: &str, right_path: &str) {
+        // `pos` is the index in the array as patched so far.
+        let mut pos = 0;
+        for step in self.align(l, r) {
+            let at = pointer(right_path, &pos.to_string());
+            match step {
+                Step::Same => pos += 1,
+                Step::Pair(i, j) => {
+                    self.value(&l[i], &r[j], &pointer(left_path, &i.to_string()), &at);
+                    pos += 1;
+                }
+                Step::Removed(i) => {
+                    self.removed(&pointer(left_path, &i.to_string()), &at, &l[i]);
+                }
+                Step::Added(j) => {
+                    self.added(&at, &r[j]);
+                    pos += 1;
+                }
+            }
+        }
+    }
+
+    /// Trims the common prefix and suffix, then aligns the rest by longest
+    /// common subsequence. Unmatched elements between two matches are
+    /// paired up first, so an edited object shows as nested changes rather
+    /// than a removal and an addition.
+    fn align(&self, l: &[Value], r: &[Value]) -> Vec<Step> {
+        let prefix = l.iter().zip(r).take_while(|(a, b)| self.same(a, b)).count();
+        let suffix = l[prefix..]
+            .iter()
+            .rev()
+            .zip(r[prefix..].iter().rev())
+            .take_while(|(a, b)| self.same(a, b))
+            .count();
+        let (ml, mr) = (&l[prefix..l.len() - suffix], &r[prefix..r.len() - suffix]);
+
+        // Matched (left, right) index pairs within the middle.
+        let matches: Vec<(usize, usize)> = if ml.len() * mr.len() <= LCS_MAX_CELLS {
+            self.lcs(ml, mr)
+        } else {
+            Vec::new()
+        };
+
+        let mut steps: Vec<Step> = (0..prefix).map(|_| Step::Same).collect();
+        let (mut i, mut j) = (0, 0);
+        for (mi, mj) in matches.into_iter().chain([(ml.len(), mr.len())]) {
+            let removed: Vec<usize> = (i..mi).map(|k| k + prefix).collect();
+            let added: Vec<usize> = (j..mj).map(|k| k + prefix).collect();
+            let paired = removed.len().min(added.len());
+            for k in 0..paired {
+                steps.push(Step::Pair(removed[k], added[k]));
+            }
+            steps.extend(removed[paired..].iter().map(|&i| Step::Removed(i)));
+            steps.extend(added[paired..].iter().map(|&j| Step::Added(j)));
+            if mi < ml.len() {
+                steps.push(Step::Same);
+            }
+            (i, j) = (mi + 1, mj + 1);
+        }
+        steps.extend((0..suffix).map(|_| Step::Same));
+        steps
+    }
+
+    fn lcs(&self, l: &[Value], r: &[Value]) -> Vec<(usize, usize)> {
+        let (n, m) = (l.len(), r.len());
+        // lengths[i][j]: LCS of l[i..] and r[j..].
+        let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i][j] = if self.same(&l[i], &r[j]) {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        let mut out = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if self.same(&l[i], &r[j]) {
+                out.push((i, j));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        out
+    }
+
+    /// Unmatched left elements are removed from the back, so earlier
+    /// indices stay valid; unmatched right elements are appended. Elements
+    /// are matched through their canonical form, so this stays linear in
+    /// the number of elements rather than quadratic.
+    fn array_as_set(&mut self, l: &[Value], r: &[Value], left_path: &str, right_path: &str) {
+        let mut unused: HashMap<String, Vec<usize>> = HashMap::new();
+        for (j, rv) in r.iter().enumerate().rev() {
+            unused.entry(canonical(rv)).or_default().push(j);
+        }
+        let mut used = vec![false; r.len()];
+        let mut unmatched_left = Vec::new();
+        for (i, lv) in l.iter().enumerate() {
+            match unused.get_mut(&canonical(lv)).and_then(Vec::pop) {
+                Some(j) => used[j] = true,
+                None => unmatched_left.push(i),
+            }
+        }
+        for &i in unmatched_left.iter().rev() {
+            let index = i.to_string();
+            self.removed(
+                &pointer(left_path, &index),
+                &pointer(right_path, &index),
+                &l[i],
+            );
+        }
+        for (j, rv) in r.iter().enumerate().filter(|(j, _)| !used[*j]) {
+            self.changes.push(Change {
+                kind: ChangeKind::Added,
+                path: pointer(right_path, &j.to_string()),
+                old_value: None,
+                value: Some(rv.clone()),
+            });
+            self.patch
+                .push(json!({ "op": "add", "path": pointer(right_path, "-"), "value": rv }));
+        }
+    }
+
+    fn removed(&mut self, left_path: &str, patch_path: &str, value: &Value) {
+        self.changes.push(Change {
+            kind: ChangeKind::Removed,
+            path: left_path.to_string(),
+            old_value: Some(value.clone()),
+            value: None,
+        });
+        self.patch
+            .push(json!({ "op": "remove", "path": patch_path }));
+    }
+
+    fn added(&mut self, path: &str, value: &Value) {
+        self.changes.push(Change {
+            kind: ChangeKind::Added,
+            path: path.to_string(),
+            old_value: None,
+            value: Some(value.clone()),
+        });
+        self.patch
+            .push(json!({ "op": "add", "path": path, "value": value }));
+    }
+
+    /// Equality, with arrays compared as multisets in set mode.
+    fn same(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Array(x), Value::Array(y)) if self.sets => {
+                x.len() == y.len() && canonical(a) == canonical(b)
+            }
+            (Value::Array(x), Value::Array(y)) => {
+                x.len() == y.len() && x.iter().zip(y).all(|(a, b)| self.same(a, b))
+            }
+            (Value::Object(x), Value::Object(y)) => {
+                x.len() == y.len()
+                    && x.iter()
+                        .all(|(k, v)| y.get(k).is_some_and(|w| self.same(v, w)))
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+/// A text form equal for exactly the values set mode treats as the same:
+/// array elements and object keys are sorted.
+fn canonical(value: &Value) -> String {
+    match value {
+        Value::Array(items) => {
+            let mut items: Vec<String> = items.iter().map(canonical).collect();
+            items.sort_unstable();
+            format!("[{}]", items.join(","))
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_unstable_by_key(|(k, _)| *k);
+            let entries: Vec<String> = entries
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", Value::from(k.as_str()), canonical(v)))
+                .collect();
+            format!("{{{}}}", entries.join(","))
+        }
+        scalar => scalar.to_string(),
+    }
+}
+
+/// RFC 7386: objects are merged key by key, removed keys become `null`,
+/// anything else is replaced whole.
+fn merge_patch(left: &Value, right: &Value) -> Value {
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            let mut patch = Map::new();
+            for key in l.keys().filter(|k| !r.contains_key(*k)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            for (key, rv) in r {
+                match l.get(key) {
+                    Some(lv) if lv == rv => {}
+                    Some(lv @ Value::Object(_)) if rv.is_object() => {
+                        patch.insert(key.clone(), merge_patch(lv, rv));
+                    }
+                    _ => {
+                        patch.insert(key.clone(), rv.clone());
+                    }
+                }
+            }
+            Value::Object(patch)
+        }
+        (_, right) => right.clone(),
+    }
+}
+
+/// Appends one RFC 6901 reference token.
+fn pointer(base: &str, token: &str) -> String {
+    format!("{}/{}", base, token.replace('~', "~0").replace('/', "~1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(left: Value, right: Value, sets: bool) -> Diff {
+        let mut diff = Diff {
+            sets,
+            changes: Vec::new(),
+            patch: Vec::new(),
+        };
+        diff.value(&left, &right, "", "");
+        diff
+    }
+
+    /// The generated patch must turn `left` into `right`.
+    fn round_trip(left: Value, right: Value) -> Vec<Value> {
+        let d = diff(left.clone(), right.clone(), false);
+        let ops: json_patch::Patch = serde_json::from_value(Value::Array(d.patch.clone())).unwrap();
+        let mut patched = left.clone();
+        json_patch::patch(&mut patched, &ops).unwrap();
+        assert_eq!(patched, right);
+        d.patch
+    }
+
+    #[test]
+    fn ordered_arrays_align_on_common_elements() {
+        let patch = round_trip(
+            json!({ "a/b": 1, "list": [1, 2, { "x": 1 }, 4], "gone": true }),
+            json!({ "a/b": 2, "list": [0, 1, 2, { "x": 2 }, 4], "new": null }),
+        );
+        assert_eq!(
+            patch,
+            [
+                json!({ "op": "replace", "path": "/a~1b", "value": 2 }),
+                json!({ "op": "remove", "path": "/gone" }),
+                json!({ "op": "add", "path": "/list/0", "value": 0 }),
+                json!({ "op": "replace", "path": "/list/3/x", "value": 2 }),
+                json!({ "op": "add", "path": "/new", "value": null }),
+            ]
+        );
+        round_trip(json!([1, 2, 3, 4, 5]), json!([5, 3, 1]));
+        round_trip(json!([[1], [2, 3]]), json!([[2, 3, 4], [1]]));
+    }
+
+    #[test]
+    fn merge_patch_nulls_removed_keys() {
+        let left = json!({ "a": { "b": 1, "c": 2 }, "d": [1, 2], "e": "x" });
+        let right = json!({ "a": { "b": 1, "c": 3 }, "d": [2], "f": true });
+        let patch = merge_patch(&left, &right);
+        assert_eq!(
+            patch,
+            json!({ "a": { "c": 3 }, "d": [2], "e": null, "f": true })
+        );
+        let mut merged = left;
+        json_patch::merge(&mut merged, &patch);
+        assert_eq!(merged, right);
+    }
+
+    #[test]
+    fn arrays_as_sets_ignore_order() {
+        let d = diff(
+            json!({ "t": [1, 2, [3, 4]] }),
+            json!({ "t": [[4, 3], 2, 1] }),
+            true,
+        );
+        assert!(d.changes.is_empty());
+
+        let d = diff(json!([1, 2, 3, 2]), json!([2, 5, 1]), true);
+        let kinds: Vec<(Value, &str)> = d
+            .changes
+            .iter()
+            .map(|c| (serde_json::to_value(&c.kind).unwrap(), c.path.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (json!("removed"), "/3"),
+                (json!("removed"), "/2"),
+                (json!("added"), "/1"),
+            ]
+        );
+
+        let big: Vec<Value> = (0..20_000)
+            .map(|i| json!({ "id": i, "tags": [i % 7, 1] }))
+            .collect();
+        let mut shuffled = big.clone();
+        shuffled.reverse();
+        shuffled[0] = json!({ "tags": [1, 0], "id": 0 });
+        let d = diff(Value::Array(big), Value::Array(shuffled), true);
+        assert_eq!(d.changes.len(), 2);
+    }
+}
@@ -0,0 +1,258 @@
+//! Turns a `serde_json` syntax error into a located, categorised
+//! diagnostic the web UI can highlight.
+
+use serde::Serialize;
+use serde_json::error::Category as SerdeCategory;
+
+/// Characters of context kept on each side of the error in `excerpt`.
+const EXCERPT_CONTEXT: usize = 40;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Diagnostic {
+    /// The request field holding the document.
+    field: String,
+    category: Category,
+    /// `serde_json`'s description, without the position.
+    pub(super) reason: String,
+    /// 1-based.
+    line: usize,
+    /// 1-based, counted in characters.
+    column: usize,
+    /// Byte offset into the document.
+    offset: usize,
+    /// The offending line with a `^` under the error.
+    excerpt: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) enum Category {
+    UnexpectedEof,
+    TrailingComma,
+    TrailingCharacters,
+    UnquotedKey,
+    SingleQuote,
+    Comment,
+    MissingComma,
+    MissingColon,
+    /// `undefined`, `NaN`, `True` and other bare words.
+    InvalidLiteral,
+    InvalidNumber,
+    InvalidEscape,
+    InvalidUnicode,
+    ControlCharacter,
+    TooDeep,
+    UnexpectedToken,
+}
+
+pub(super) fn diagnose(raw: &str, field: &str, err: &serde_json::Error) -> Diagnostic {
+    let display = err.to_string();
+    let reason = match display.rfind(" at line ") {
+        Some(at) if err.line() > 0 => display[..at].to_string(),
+        _ => display,
+    };
+
+    // `serde_json` reports the column just past the offending byte; at the
+    // end of input, point just past the last token instead, for a misspelt
+    // `true` / `false` / `null` at the start of the word, and for a
+    // trailing comma at the comma (below).
+    let offset = if err.classify() == SerdeCategory::Eof {
+        raw.trim_end().len()
+    } else {
+        let start = line_start(raw, err.line());
+        let at = floor_char_boundary(raw, (start + err.column().saturating_sub(1)).min(raw.len()));
+        if reason == "expected ident" {
+            raw[..at]
+                .rfind(|c: char| !c.is_alphanumeric())
+                .map_or(0, |i| i + 1)
+        } else {
+            at
+        }
+    };
+    let category = categorize(err.classify(), &reason, &raw[offset..]);
+    // Point at the comma itself, where lenient mode reports removing it.
+    let offset = match category {
+        Category::TrailingComma => raw[..offset].rfind(',').unwrap_or(offset),
+        _ => offset,
+    };
+    Diagnostic::at(raw, field, offset, category, reason)
+}
+
+impl Diagnostic {
+    /// A diagnostic for the error at byte `offset` of `raw`.
+    pub(super) fn at(
+        raw: &str,
+        field: &str,
+        offset: usize,
+        category: Category,
+        reason: String,
+    ) -> Self {
+        let (line, column) = position(raw, offset);
+        let start = raw[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let text = raw[start..].split('\n').next().unwrap_or_default();
+        Self {
+            field: field.to_string(),
+            category,
+            reason,
+            line,
+            column,
+            offset,
+            excerpt: excerpt(text.trim_end_matches('\r'), line, column),
+        }
+    }
+}
+
+/// 1-based line and character column of byte `offset`.
+pub(super) fn position(raw: &str, offset: usize) -> (usize, usize) {
+    let start = raw[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = 1 + raw[..offset].matches('\n').count();
+    (line, raw[start..offset].chars().count() + 1)
+}
+
+/// `rest` is the document from the error position on.
+fn categorize(class: SerdeCategory, reason: &str, rest: &str) -> Category {
+    if class == SerdeCategory::Eof {
+        return Category::UnexpectedEof;
+    }
+    let comment = rest.starts_with("//") || rest.starts_with("/*");
+    let at = rest.chars().next().unwrap_or_default();
+    match reason {
+        _ if comment => Category::Comment,
+        "trailing comma" => Category::TrailingComma,
+        "trailing characters" => Category::TrailingCharacters,
+        "expected `:`" => Category::MissingColon,
+        "expected `,` or `}`" | "expected `,` or `]`" => Category::MissingComma,
+        "key must be a string" | "expected value" | "expected ident" => match at {
+            '\'' => Category::SingleQuote,
+            c if reason.starts_with("key") && (c.is_alphabetic() || c == '_' || c == '$') => {
+                Category::UnquotedKey
+            }
+            c if c.is_alphabetic() => Category::InvalidLiteral,
+            '+' | '.' => Category::InvalidNumber,
+            _ => Category::UnexpectedToken,
+        },
+        "invalid number" | "number out of range" => Category::InvalidNumber,
+        "invalid escape" | "unexpected end of hex escape" => Category::InvalidEscape,
+        "invalid unicode code point" | "lone leading surrogate in hex escape" => {
+            Category::InvalidUnicode
+        }
+        "recursion limit exceeded" => Category::TooDeep,
+        r if r.starts_with("control character") => Category::ControlCharacter,
+        _ => Category::UnexpectedToken,
+    }
+}
+
+/// Byte offset where 1-based `line` begins.
+fn line_start(raw: &str, line: usize) -> usize {
+    raw.match_indices('\n')
+        .nth(line.saturating_sub(2))
+        .filter(|_| line > 1)
+        .map_or(0, |(i, _)| i + 1)
+}
+
+fn floor_char_boundary(raw: &str, mut i: usize) -> usize {
+    while !raw.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+/// ```text
+///  3 |   "b": 2,}
+///    |         ^
+/// ```
+/// Long lines are cut to `EXCERPT_CONTEXT` characters around the error.
+fn excerpt(text: &str, line: usize, column: usize) -> String {
+    let chars: Vec<char> = text
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let at = column - 1;
+    let from = at.saturating_sub(EXCERPT_CONTEXT);
+    let to = chars.len().min(at + EXCERPT_CONTEXT + 1);
+
+    let mut shown = String::new();
+    if from > 0 {
+        shown.push('…');
+    }
+    shown.extend(&chars[from..to]);
+    if to < chars.len() {
+        shown.push('…');
+    }
+    let caret = at - from + usize::from(from > 0);
+
+    let number = line.to_string();
+    let gutter = " ".repeat(number.len());
+    format!(
+        "{} | {}\n{} | {}^",
+        number,
+        shown,
+        gutter,
+        " ".repeat(caret)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(raw: &str) -> Diagnostic {
+        let err = serde_json::from_str::<serde_json::Value>(raw).unwrap_err();
+        diagnose(raw, "json", &err)
+    }
+
+    #[test]
+    fn locates_and_categorizes() {
+        let cases = [
+            ("{\"a\": 1,}", Category::TrailingComma, 1, 8),
+            ("[1, 2,]", Category::TrailingComma, 1, 6),
+            ("[1,\n]", Category::TrailingComma, 1, 3),
+            // Same spot as lenient mode's "removed trailing comma".
+            ("{\"a\":1,}", Category::TrailingComma, 1, 7),
+            ("{a: 1}", Category::UnquotedKey, 1, 2),
+            ("{'a': 1}", Category::SingleQuote, 1, 2),
+            ("{\"a\": 'x'}", Category::SingleQuote, 1, 7),
+            ("{\n  // note\n  \"a\": 1\n}", Category::Comment, 2, 3),
+            ("{\"a\": 1 // note\n}", Category::Comment, 1, 9),
+            ("{\"a\": 1 \"b\": 2}", Category::MissingComma, 1, 9),
+            ("{\"a\" 1}", Category::MissingColon, 1, 6),
+            ("{\"a\": undefined}", Category::InvalidLiteral, 1, 7),
+            ("[+1]", Category::InvalidNumber, 1, 2),
+            ("{\"a\": \"\\x\"}", Category::InvalidEscape, 1, 9),
+            ("{\"a\": 1} x", Category::TrailingCharacters, 1, 10),
+            ("{\"a\": [1,\n  2\n", Category::UnexpectedEof, 2, 4),
+            ("{\"é\": ü}", Category::InvalidLiteral, 1, 7),
+            ("[nul]", Category::InvalidLiteral, 1, 2),
+            ("[01]", Category::InvalidNumber, 1, 3),
+            ("[.5]", Category::InvalidNumber, 1, 2),
+            ("[\"\\udc00\"]", Category::InvalidUnicode, 1, 8),
+            ("[\"a\tb\"]", Category::ControlCharacter, 1, 4),
+        ];
+        for (raw, category, line, column) in cases {
+            let d = check(raw);
+            assert_eq!(
+                (d.category, d.line, d.column),
+                (category, line, column),
+                "{raw:?}: {}",
+                d.reason
+            );
+        }
+    }
+
+    #[test]
+    fn excerpt_marks_the_column() {
+        let d = check("{\n  \"a\": 1,\n  \"b\": 2,\n}");
+        assert_eq!(d.reason, "trailing comma");
+        assert_eq!(d.offset, 20);
+        assert_eq!(d.excerpt, "3 |   \"b\": 2,\n  |         ^");
+
+        let long = format!("[{}nope]", "1, ".repeat(100));
+        let d = check(&long);
+        assert_eq!(d.column, 302);
+        let (first, second) = d.excerpt.split_once('\n').unwrap();
+        assert!(first.starts_with("1 | …") && first.ends_with("nope]"));
+        let caret = second.chars().position(|c| c == '^');
+        assert_eq!(caret, first.chars().position(|c| c == 'n'));
+    }
+}
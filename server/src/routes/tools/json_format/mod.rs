@@ -0,0 +1,83 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ApiError, ApiResult};
+
+mod diagnose;
+mod repair;
+
+#[derive(Deserialize)]
+pub(super) struct JsonFormatReq {
+    json: String,
+    #[serde(default = "default_indent")]
+    indent: u8,
+    /// Accept almost-JSON (JSON5, comments, single quotes, trailing
+    /// commas, ...) and report what had to be fixed.
+    #[serde(default)]
+    lenient: bool,
+}
+
+#[derive(Serialize)]
+pub(super) struct JsonFormatResp {
+    formatted: String,
+    /// Only in lenient mode; empty when the input was strict JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repairs: Option<Vec<repair::Repair>>,
+}
+
+fn default_indent() -> u8 {
+    2
+}
+
+/// Parses the JSON text submitted in `field`; shared by the JSON tools.
+/// Syntax errors carry a [`diagnose::Diagnostic`] as `details`.
+pub(super) fn parse(raw: &str, field: &str) -> Result<serde_json::Value, ApiError> {
+    serde_json::from_str(raw).map_err(|e| ApiError::BadRequestDetails {
+        message: format!("invalid {}: {}", field, e),
+        details: serde_json::to_value(diagnose::diagnose(raw, field, &e)).unwrap_or_default(),
+    })
+}
+
+/// Parses `raw` leniently; strict JSON takes the fast path.
+fn parse_lenient(
+    raw: &str,
+    field: &str,
+) -> Result<(serde_json::Value, Vec<repair::Repair>), ApiError> {
+    if let Ok(value) = serde_json::from_str(raw) {
+        return Ok((value, Vec::new()));
+    }
+    repair::repair(raw).map_err(|failure| {
+        let details =
+            diagnose::Diagnostic::at(raw, field, failure.offset, failure.category, failure.reason);
+        let (line, column) = diagnose::position(raw, failure.offset);
+        ApiError::BadRequestDetails {
+            message: format!(
+                "cannot repair {}: {} at line {} column {}",
+                field, details.reason, line, column
+            ),
+            details: serde_json::to_value(details).unwrap_or_default(),
+        }
+    })
+}
+
+pub async fn handle(Json(req): Json<JsonFormatReq>) -> ApiResult<JsonFormatResp> {
+    let (value, repairs) = if req.lenient {
+        let (value, repairs) = parse_lenient(&req.json, "json")?;
+        (value, Some(repairs))
+    } else {
+        (parse(&req.json, "json")?, None)
+    };
+    let formatted = if req.indent == 0 {
+        serde_json::to_string(&value).unwrap_or_else(|_| req.json.clone())
+    } else {
+        let indent = " ".repeat(req.indent as usize);
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        let mut buf = Vec::new();
+        let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
+        value
+            .serialize(&mut ser)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        String::from_utf8(buf).unwrap_or_default()
+    };
+    Ok(Json(JsonFormatResp { formatted, repairs }))
+}
@@ -0,0 +1,675 @@
+//! A forgiving JSON parser for almost-JSON: JSON5 / JSONC, JavaScript
+//! object literals and Python reprs. It records every deviation from
+//! strict JSON it had to fix.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::diagnose::Category;
+
+/// Nesting limit, the same as `serde_json`'s.
+const MAX_DEPTH: usize = 128;
+
+#[derive(Serialize)]
+pub(super) struct Repair {
+    kind: RepairKind,
+    message: String,
+    /// 1-based.
+    line: usize,
+    /// 1-based, counted in characters.
+    column: usize,
+    /// Byte offset into the input.
+    offset: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum RepairKind {
+    Comment,
+    TrailingComma,
+    /// A comma with nothing before it, e.g. `[,1]` or `[1,,2]`.
+    ExtraComma,
+    MissingComma,
+    UnquotedKey,
+    SingleQuote,
+    /// JSON5 / JavaScript escapes such as `\x41`, `\'` or line continuations.
+    Escape,
+    ControlCharacter,
+    /// Hexadecimal, leading `+`, leading zeros or a bare decimal point.
+    Number,
+    /// `NaN` and `Infinity`, which JSON cannot represent, become `null`.
+    NonFiniteNumber,
+    /// `True`, `False`, `None` and `undefined`.
+    Literal,
+    /// An object or array left open at the end of the input.
+    Unclosed,
+}
+
+/// Why the input could not be repaired.
+pub(super) struct Failure {
+    pub offset: usize,
+    pub category: Category,
+    pub reason: String,
+}
+
+type Parsed<T> = Result<T, Failure>;
+
+/// Parses `raw`, returning the value and the repairs in input order.
+pub(super) fn repair(raw: &str) -> Parsed<(Value, Vec<Repair>)> {
+    let mut parser = Parser {
+        src: raw,
+        pos: 0,
+        depth: 0,
+        repairs: Vec::new(),
+    };
+    parser.skip_blank();
+    let value = parser.value()?;
+    parser.skip_blank();
+    if let Some(c) = parser.peek() {
+        return Err(parser.fail(
+            Category::TrailingCharacters,
+            format!("unexpected `{}` after the document", c),
+        ));
+    }
+
+    let mut repairs = parser.repairs;
+    repairs.sort_by_key(|r| r.offset);
+    locate(raw, &mut repairs);
+    Ok((value, repairs))
+}
+
+/// Fills in line and column for repairs sorted by offset, in one pass over
+/// `raw` (same numbering as [`super::diagnose::position`]).
+fn locate(raw: &str, repairs: &mut [Repair]) {
+    let (mut pos, mut line, mut column) = (0, 1, 1);
+    for r in repairs {
+        for c in raw[pos..r.offset].chars() {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        pos = r.offset;
+        (r.line, r.column) = (line, column);
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    /// Byte offset of the next character.
+    pos: usize,
+    depth: usize,
+    repairs: Vec<Repair>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn fail(&self, category: Category, reason: impl Into<String>) -> Failure {
+        Failure {
+            offset: self.pos,
+            category,
+            reason: reason.into(),
+        }
+    }
+
+    fn record(&mut self, offset: usize, kind: RepairKind, message: impl Into<String>) {
+        self.repairs.push(Repair {
+            kind,
+            message: message.into(),
+            line: 0,
+            column: 0,
+            offset,
+        });
+    }
+
+    /// Skips whitespace (including a BOM) and `//`, `/* */` and `#` comments.
+    fn skip_blank(&mut self) {
+        loop {
+            let start = self.pos;
+            let rest = self.rest();
+            if let Some(c) = rest
+                .chars()
+                .next()
+                .filter(|c| c.is_whitespace() || *c == '\u{feff}')
+            {
+                self.pos += c.len_utf8();
+                continue;
+            }
+            let end = if rest.starts_with("//") || rest.starts_with('#') {
+                rest.find('\n').unwrap_or(rest.len())
+            } else if let Some(body) = rest.strip_prefix("/*") {
+                body.find("*/").map_or(rest.len(), |i| i + 4)
+            } else {
+                return;
+            };
+            self.pos += end;
+            self.record(start, RepairKind::Comment, "removed comment");
+        }
+    }
+
+    fn value(&mut self) -> Parsed<Value> {
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some(quote @ ('"' | '\'')) => self.string(quote).map(Value::String),
+            Some('0'..='9' | '-' | '+' | '.') => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' || c == '$' => self.word(),
+            Some(c) => Err(self.fail(Category::UnexpectedToken, format!("unexpected `{}`", c))),
+            None => Err(self.fail(Category::UnexpectedEof, "unexpected end of input")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Parsed<Value>) -> Parsed<Value> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.fail(Category::TooDeep, "recursion limit exceeded"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Parsed<Value> {
+        let open = self.pos;
+        self.bump();
+        let mut map = Map::new();
+        let mut comma = None;
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                Some('}') => {
+                    if let Some(at) = comma {
+                        self.record(at, RepairKind::TrailingComma, "removed trailing comma");
+                    }
+                    self.bump();
+                    return Ok(Value::Object(map));
+                }
+                Some(',') => {
+                    self.record(self.pos, RepairKind::ExtraComma, "removed extra comma");
+                    self.bump();
+                    continue;
+                }
+                None => {
+                    self.record(open, RepairKind::Unclosed, "closed unterminated `{`");
+                    return Ok(Value::Object(map));
+                }
+                Some(_) => {}
+            }
+
+            let key = self.key()?;
+            self.skip_blank();
+            if self.peek() != Some(':') {
+                return Err(self.fail(Category::MissingColon, "expected `:` after key"));
+            }
+            self.bump();
+            self.skip_blank();
+            let value = self.value()?;
+            map.insert(key, value);
+
+            comma = None;
+            self.skip_blank();
+            match self.peek() {
+                Some(',') => {
+                    comma = Some(self.pos);
+                    self.bump();
+                }
+                Some('}') | None => {}
+                Some(_) => {
+                    self.record(self.pos, RepairKind::MissingComma, "inserted missing comma")
+                }
+            }
+        }
+    }
+
+    fn key(&mut self) -> Parsed<String> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => self.string(quote),
+            Some(c) if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let start = self.pos;
+                let key = self.identifier().to_string();
+                self.record(
+                    start,
+                    RepairKind::UnquotedKey,
+                    format!("quoted key `{}`", key),
+                );
+                Ok(key)
+            }
+            Some(c) => Err(self.fail(
+                Category::UnexpectedToken,
+                format!("expected a key, found `{}`", c),
+            )),
+            None => Err(self.fail(Category::UnexpectedEof, "unexpected end of input")),
+        }
+    }
+
+    fn array(&mut self) -> Parsed<Value> {
+        let open = self.pos;
+        self.bump();
+        let mut items = Vec::new();
+        let mut comma = None;
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                Some(']') => {
+                    if let Some(at) = comma {
+                        self.record(at, RepairKind::TrailingComma, "removed trailing comma");
+                    }
+                    self.bump();
+                    return Ok(Value::Array(items));
+                }
+                Some(',') => {
+                    self.record(self.pos, RepairKind::ExtraComma, "removed extra comma");
+                    self.bump();
+                    continue;
+                }
+                None => {
+                    self.record(open, RepairKind::Unclosed, "closed unterminated `[`");
+                    return Ok(Value::Array(items));
+                }
+                Some(_) => {}
+            }
+
+            items.push(self.value()?);
+
+            comma = None;
+            self.skip_blank();
+            match self.peek() {
+                Some(',') => {
+                    comma = Some(self.pos);
+                    self.bump();
+                }
+                Some(']') | None => {}
+                Some(_) => {
+                    self.record(self.pos, RepairKind::MissingComma, "inserted missing comma")
+                }
+            }
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Parsed<String> {
+        let open = self.pos;
+        self.bump();
+        if quote == '\'' {
+            self.record(
+                open,
+                RepairKind::SingleQuote,
+                "converted single-quoted string",
+            );
+        }
+        let mut out = String::new();
+        loop {
+            let at = self.pos;
+            match self.bump() {
+                None => {
+                    self.pos = open;
+                    return Err(self.fail(Category::UnexpectedEof, "unterminated string"));
+                }
+                Some(c) if c == quote => return Ok(out),
+                Some('\\') => self.escape(at, quote, &mut out)?,
+                Some(c) if (c as u32) < 0x20 => {
+                    self.record(
+                        at,
+                        RepairKind::ControlCharacter,
+                        "escaped control character",
+                    );
+                    out.push(c);
+                }
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    /// Decodes the escape after the backslash at `at` into `out`.
+    fn escape(&mut self, at: usize, quote: char, out: &mut String) -> Parsed<()> {
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Err(self.fail(Category::UnexpectedEof, "unterminated string")),
+        };
+        let decoded = match c {
+            '"' | '\\' | '/' => Some(c),
+            'b' => Some('\u{8}'),
+            'f' => Some('\u{c}'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'u' => {
+                out.push(self.unicode_escape(at)?);
+                return Ok(());
+            }
+            _ => None,
+        };
+        if let Some(decoded) = decoded {
+            out.push(decoded);
+            return Ok(());
+        }
+
+        // Not JSON: JSON5 / JavaScript escapes.
+        let message = match c {
+            '\'' if quote == '\'' => {
+                out.push(c);
+                return Ok(());
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => "removed line continuation".to_string(),
+            '\r' => {
+                if self.peek() == Some('\n') {
+                    self.bump();
+                }
+                "removed line continuation".to_string()
+            }
+            'v' => {
+                out.push('\u{b}');
+                "decoded `\\v`".to_string()
+            }
+            '0' if !self.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                out.push('\0');
+                "decoded `\\0`".to_string()
+            }
+            'x' => {
+                let code = self.hex(2)?;
+                out.push(char::from(code as u8));
+                format!("decoded `\\x{:02x}`", code)
+            }
+            c => {
+                out.push(c);
+                format!("replaced unknown escape `\\{}` with `{}`", c, c)
+            }
+        };
+        self.record(at, RepairKind::Escape, message);
+        Ok(())
+    }
+
+    /// `\uXXXX`, combining surrogate pairs; a lone surrogate becomes U+FFFD.
+    fn unicode_escape(&mut self, at: usize) -> Parsed<char> {
+        let high = self.hex(4)?;
+        if !(0xd800..0xe000).contains(&high) {
+            return Ok(char::from_u32(high).unwrap_or_default());
+        }
+        if (0xd800..0xdc00).contains(&high) && self.rest().starts_with("\\u") {
+            let resume = self.pos;
+            self.pos += 2;
+            let low = self.hex(4)?;
+            if (0xdc00..0xe000).contains(&low) {
+                let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                return Ok(char::from_u32(code).unwrap_or_default());
+            }
+            self.pos = resume;
+        }
+        self.record(
+            at,
+            RepairKind::Escape,
+            format!("replaced lone surrogate `\\u{:04x}` with U+FFFD", high),
+        );
+        Ok(char::REPLACEMENT_CHARACTER)
+    }
+
+    fn hex(&mut self, digits: usize) -> Parsed<u32> {
+        let text = self.rest().get(..digits).unwrap_or_default();
+        match u32::from_str_radix(text, 16) {
+            Ok(code) if text.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                self.pos += digits;
+                Ok(code)
+            }
+            _ => Err(self.fail(
+                Category::InvalidEscape,
+                format!("expected {} hex digits", digits),
+            )),
+        }
+    }
+
+    fn number(&mut self) -> Parsed<Value> {
+        let start = self.pos;
+        let negative = match self.peek() {
+            Some('-') => {
+                self.bump();
+                true
+            }
+            Some('+') => {
+                self.bump();
+                false
+            }
+            _ => false,
+        };
+        let sign = if negative { "-" } else { "" };
+        let rest = self.rest();
+
+        for word in ["Infinity", "NaN"] {
+            if rest.starts_with(word) {
+                self.pos += word.len();
+                self.record(
+                    start,
+                    RepairKind::NonFiniteNumber,
+                    format!("replaced `{}` with null", &self.src[start..self.pos]),
+                );
+                return Ok(Value::Null);
+            }
+        }
+
+        let normalized = if let Some(hex) = rest.strip_prefix("0x").or(rest.strip_prefix("0X")) {
+            let digits = hex.len()
+                - hex
+                    .trim_start_matches(|c: char| c.is_ascii_hexdigit())
+                    .len();
+            let value = u64::from_str_radix(&hex[..digits], 16)
+                .map_err(|_| self.fail(Category::InvalidNumber, "invalid hexadecimal number"))?;
+            self.pos += 2 + digits;
+            format!("{}{}", sign, value)
+        } else {
+            let int = self.digits();
+            let frac = if self.peek() == Some('.') {
+                self.bump();
+                Some(self.digits())
+            } else {
+                None
+            };
+            let exp = if let Some(e @ ('e' | 'E')) = self.peek() {
+                let at = self.pos;
+                self.bump();
+                let exp_sign = match self.peek() {
+                    Some(s @ ('+' | '-')) => {
+                        self.bump();
+                        s.to_string()
+                    }
+                    _ => String::new(),
+                };
+                let digits = self.digits();
+                if digits.is_empty() {
+                    self.pos = at;
+                    return Err(self.fail(Category::InvalidNumber, "exponent has no digits"));
+                }
+                format!("{}{}{}", e, exp_sign, digits)
+            } else {
+                String::new()
+            };
+            if int.is_empty() && frac.is_none_or(str::is_empty) {
+                self.pos = start;
+                return Err(self.fail(Category::InvalidNumber, "number has no digits"));
+            }
+
+            let int = int.trim_start_matches('0');
+            let int = if int.is_empty() { "0" } else { int };
+            match frac.filter(|f| !f.is_empty()) {
+                Some(frac) => format!("{}{}.{}{}", sign, int, frac, exp),
+                None => format!("{}{}{}", sign, int, exp),
+            }
+        };
+
+        let text = &self.src[start..self.pos];
+        let value: Value = serde_json::from_str(&normalized)
+            .map_err(|_| self.fail(Category::InvalidNumber, "invalid number"))?;
+        if text != normalized {
+            self.record(
+                start,
+                RepairKind::Number,
+                format!("rewrote `{}` as `{}`", text, normalized),
+            );
+        }
+        Ok(value)
+    }
+
+    fn digits(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn identifier(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest.len()
+            - rest
+                .trim_start_matches(|c: char| c.is_alphanumeric() || c == '_' || c == '$')
+                .len();
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn word(&mut self) -> Parsed<Value> {
+        let start = self.pos;
+        let word = self.identifier();
+        let (value, strict) = match word {
+            "true" => (Value::Bool(true), true),
+            "false" => (Value::Bool(false), true),
+            "null" => (Value::Null, true),
+            "True" => (Value::Bool(true), false),
+            "False" => (Value::Bool(false), false),
+            "None" | "undefined" => (Value::Null, false),
+            "NaN" | "Infinity" => {
+                self.pos = start;
+                return self.number();
+            }
+            _ => {
+                let reason = format!("unexpected word `{}`", word);
+                self.pos = start;
+                return Err(self.fail(Category::InvalidLiteral, reason));
+            }
+        };
+        if !strict {
+            let message = format!("replaced `{}` with `{}`", word, value);
+            self.record(start, RepairKind::Literal, message);
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn kinds(raw: &str) -> (Value, Vec<RepairKind>) {
+        let (value, repairs) = repair(raw).unwrap_or_else(|f| panic!("{}", f.reason));
+        (value, repairs.into_iter().map(|r| r.kind).collect())
+    }
+
+    #[test]
+    fn repairs_almost_json() {
+        use RepairKind::*;
+
+        let (value, found) = kinds(
+            "// config\n{\n  name: 'magic\\'box', /* inline */\n  tags: ['a', 'b',],\n  \"n\": +.5\n  hex: 0x1F,\n}",
+        );
+        assert_eq!(
+            value,
+            json!({ "name": "magic'box", "tags": ["a", "b"], "n": 0.5, "hex": 31 })
+        );
+        assert_eq!(
+            found,
+            [
+                Comment,
+                UnquotedKey,
+                SingleQuote,
+                Comment,
+                UnquotedKey,
+                SingleQuote,
+                SingleQuote,
+                TrailingComma,
+                Number,
+                MissingComma,
+                UnquotedKey,
+                Number,
+                TrailingComma
+            ]
+        );
+
+        let (value, found) = kinds("{'ok': True, 'err': None, 'n': NaN, 'm': -Infinity");
+        assert_eq!(
+            value,
+            json!({ "ok": true, "err": null, "n": null, "m": null })
+        );
+        assert_eq!(
+            found,
+            [
+                Unclosed,
+                SingleQuote,
+                Literal,
+                SingleQuote,
+                Literal,
+                SingleQuote,
+                NonFiniteNumber,
+                SingleQuote,
+                NonFiniteNumber
+            ]
+        );
+
+        let (value, found) = kinds("[\"a\tb\\x41\\d\", 007, 5., [1,,2]]");
+        assert_eq!(value, json!(["a\tbAd", 7, 5, [1, 2]]));
+        assert_eq!(
+            found,
+            [ControlCharacter, Escape, Escape, Number, Number, ExtraComma]
+        );
+    }
+
+    #[test]
+    fn locates_repairs_like_diagnostics() {
+        let raw = "{\n  a: 'é',\n  // note\n  b: [1,,2,],\n}";
+        let (_, repairs) = repair(raw).unwrap_or_else(|f| panic!("{}", f.reason));
+        assert!(repairs.len() > 3);
+        for r in repairs {
+            let expected = super::super::diagnose::position(raw, r.offset);
+            assert_eq!((r.line, r.column), expected, "{:?}", r.kind);
+        }
+    }
+
+    #[test]
+    fn strict_json_needs_no_repairs() {
+        let raw = r#"{"a": [1, -2.5e3, "é😀", true, null], "b": {}}"#;
+        let (value, found) = kinds(raw);
+        assert_eq!(value, serde_json::from_str::<Value>(raw).unwrap());
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn reports_what_cannot_be_repaired() {
+        for (raw, category, offset) in [
+            ("{\"a\" 1}", Category::MissingColon, 5),
+            ("[1, foo]", Category::InvalidLiteral, 4),
+            ("\"abc", Category::UnexpectedEof, 0),
+            ("{} {}", Category::TrailingCharacters, 3),
+            ("[1 }", Category::UnexpectedToken, 3),
+            ("", Category::UnexpectedEof, 0),
+        ] {
+            let failure = repair(raw).err().expect(raw);
+            assert_eq!(
+                (failure.category, failure.offset),
+                (category, offset),
+                "{raw:?}"
+            );
+        }
+    }
+}
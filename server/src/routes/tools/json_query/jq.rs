@@ -0,0 +1,858 @@
+//! A small jq subset: paths (`.a`, `."b c"`, `.[0]`, `.[-1]`, `.["k"]`,
+//! `.[]`, `..`), pipes, `,`, `[...]`, literals, comparisons, `and` / `or`
+//! and the builtins `select`, `map`, `keys`, `length`, `has`, `type` and
+//! `not`. Every output remembers where in the input it came from, as long
+//! as it was reached by path expressions only.
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+use super::Seg;
+
+/// Stops filters like `[..] | map(..)` on large documents from running away.
+const MAX_OUTPUTS: usize = 100_000;
+
+/// Nesting limit for the parser: parentheses, brackets, function
+/// arguments and chains of `|`, `,`, `and`, `or` and postfix indexing all
+/// count, so the filter tree (and dropping it) stays shallow.
+const MAX_DEPTH: usize = 64;
+
+/// Limit on nested `eval` calls. Pipes nest through their `emit`
+/// callbacks, so this is counted at run time rather than from the tree.
+const MAX_EVAL_DEPTH: usize = 2 * MAX_DEPTH;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Identity,
+    Recurse,
+    Literal(Value),
+    /// `target[key]`, with `key` evaluated against the same input.
+    Index(Box<Filter>, Box<Filter>),
+    Iterate(Box<Filter>),
+    Pipe(Box<Filter>, Box<Filter>),
+    Comma(Box<Filter>, Box<Filter>),
+    Collect(Box<Filter>),
+    Compare(CmpOp, Box<Filter>, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Select(Box<Filter>),
+    Map(Box<Filter>),
+    Has(Box<Filter>),
+    Keys,
+    Length,
+    Type,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// One output: the value, and its location in the input when it has one.
+#[derive(Debug)]
+pub struct Output {
+    pub value: Value,
+    pub path: Option<Vec<Seg>>,
+}
+
+/// Parses and runs `filter` against `input`.
+pub fn run(filter: &str, input: &Value) -> Result<Vec<Output>, String> {
+    let filter = parse(filter)?;
+    let mut outputs = Vec::new();
+    eval(
+        &filter,
+        Output {
+            value: input.clone(),
+            path: Some(Vec::new()),
+        },
+        &Cell::new(0),
+        &mut |out| {
+            outputs.push(out);
+            if outputs.len() > MAX_OUTPUTS {
+                return Err(format!("too many results (max {})", MAX_OUTPUTS));
+            }
+            Ok(())
+        },
+    )?;
+    Ok(outputs)
+}
+
+/// `depth` counts the `eval` calls currently on the stack.
+fn eval(
+    filter: &Filter,
+    input: Output,
+    depth: &Cell<usize>,
+    emit: &mut dyn FnMut(Output) -> Result<(), String>,
+) -> Result<(), String> {
+    if depth.get() >= MAX_EVAL_DEPTH {
+        return Err(format!("filter nested too deeply (max {})", MAX_EVAL_DEPTH));
+    }
+    depth.set(depth.get() + 1);
+    let result = eval_filter(filter, input, depth, emit);
+    depth.set(depth.get() - 1);
+    result
+}
+
+fn eval_filter(
+    filter: &Filter,
+    input: Output,
+    depth: &Cell<usize>,
+    emit: &mut dyn FnMut(Output) -> Result<(), String>,
+) -> Result<(), String> {
+    match filter {
+        Filter::Identity => emit(input),
+        Filter::Recurse => recurse(input, emit),
+        Filter::Literal(value) => emit(computed(value.clone())),
+        Filter::Index(target, key) => {
+            let keys = collect(key, &input, depth)?;
+            eval(target, input, depth, &mut |container| {
+                for key in &keys {
+                    emit(index(&container, &key.value)?)?;
+                }
+                Ok(())
+            })
+        }
+        Filter::Iterate(target) => eval(target, input, depth, &mut |container| {
+            let path = |seg: Seg| {
+                container.path.as_ref().map(|p| {
+                    let mut p = p.clone();
+                    p.push(seg);
+                    p
+                })
+            };
+            match &container.value {
+                Value::Array(items) => items.iter().enumerate().try_for_each(|(i, item)| {
+                    emit(Output {
+                        value: item.clone(),
+                        path: path(Seg::Index(i)),
+                    })
+                }),
+                Value::Object(fields) => fields.iter().try_for_each(|(key, value)| {
+                    emit(Output {
+                        value: value.clone(),
+                        path: path(Seg::Key(key.clone())),
+                    })
+                }),
+                other => Err(format!("cannot iterate over {}", type_name(other))),
+            }
+        }),
+        Filter::Pipe(left, right) => {
+            eval(left, input, depth, &mut |mid| eval(right, mid, depth, emit))
+        }
+        Filter::Comma(left, right) => {
+            eval(left, share(&input), depth, emit)?;
+            eval(right, input, depth, emit)
+        }
+        Filter::Collect(inner) => {
+            let items = collect(inner, &input, depth)?;
+            emit(computed(Value::Array(
+                items.into_iter().map(|o| o.value).collect(),
+            )))
+        }
+        Filter::Compare(op, left, right) => {
+            let rights = collect(right, &input, depth)?;
+            eval(left, input, depth, &mut |l| {
+                for r in &rights {
+                    let ord = compare(&l.value, &r.value);
+                    let result = match op {
+                        CmpOp::Eq => ord == Ordering::Equal,
+                        CmpOp::Ne => ord != Ordering::Equal,
+                        CmpOp::Lt => ord == Ordering::Less,
+                        CmpOp::Le => ord != Ordering::Greater,
+                        CmpOp::Gt => ord == Ordering::Greater,
+                        CmpOp::Ge => ord != Ordering::Less,
+                    };
+                    emit(computed(Value::Bool(result)))?;
+                }
+                Ok(())
+            })
+        }
+        Filter::And(left, right) | Filter::Or(left, right) => {
+            let is_and = matches!(filter, Filter::And(..));
+            let rights = collect(right, &input, depth)?;
+            eval(left, input, depth, &mut |l| {
+                // Short-circuits like jq: the right side only matters when
+                // the left side does not decide.
+                if truthy(&l.value) != is_and {
+                    return emit(computed(Value::Bool(!is_and)));
+                }
+                for r in &rights {
+                    emit(computed(Value::Bool(truthy(&r.value))))?;
+                }
+                Ok(())
+            })
+        }
+        Filter::Select(cond) => {
+            let keep = collect(cond, &input, depth)?
+                .into_iter()
+                .filter(|c| truthy(&c.value))
+                .count();
+            for _ in 0..keep {
+                emit(share(&input))?;
+            }
+            Ok(())
+        }
+        Filter::Map(inner) => {
+            let each = Filter::Collect(Box::new(Filter::Pipe(
+                Box::new(Filter::Iterate(Box::new(Filter::Identity))),
+                inner.clone(),
+            )));
+            eval(&each, input, depth, emit)
+        }
+        Filter::Has(key) => {
+            for key in collect(key, &input, depth)? {
+                let found = match (&input.value, &key.value) {
+                    (Value::Object(fields), Value::String(k)) => fields.contains_key(k),
+                    (Value::Array(items), Value::Number(n)) => {
+                        n.as_u64().is_some_and(|i| (i as usize) < items.len())
+                    }
+                    (value, key) => {
+                        return Err(format!(
+                            "cannot check whether {} has a {} key",
+                            type_name(value),
+                            type_name(key)
+                        ))
+                    }
+                };
+                emit(computed(Value::Bool(found)))?;
+            }
+            Ok(())
+        }
+        Filter::Keys => {
+            let keys = match &input.value {
+                Value::Object(fields) => fields.keys().map(|k| Value::from(k.as_str())).collect(),
+                Value::Array(items) => (0..items.len()).map(Value::from).collect(),
+                other => return Err(format!("{} has no keys", type_name(other))),
+            };
+            emit(computed(Value::Array(keys)))
+        }
+        Filter::Length => {
+            let length = match &input.value {
+                Value::Null => Value::from(0),
+                Value::Number(n) => match n.as_i64() {
+                    Some(i) => Value::from(i.unsigned_abs()),
+                    None => Value::from(n.as_f64().unwrap_or_default().abs()),
+                },
+                Value::String(s) => Value::from(s.chars().count()),
+                Value::Array(items) => Value::from(items.len()),
+                Value::Object(fields) => Value::from(fields.len()),
+                Value::Bool(_) => return Err("boolean has no length".into()),
+            };
+            emit(computed(length))
+        }
+        Filter::Type => emit(computed(Value::from(type_name(&input.value)))),
+        Filter::Not => emit(computed(Value::Bool(!truthy(&input.value)))),
+    }
+}
+
+fn collect(filter: &Filter, input: &Output, depth: &Cell<usize>) -> Result<Vec<Output>, String> {
+    let mut out = Vec::new();
+    eval(filter, share(input), depth, &mut |o| {
+        out.push(o);
+        Ok(())
+    })?;
+    Ok(out)
+}
+
+fn share(output: &Output) -> Output {
+    Output {
+        value: output.value.clone(),
+        path: output.path.clone(),
+    }
+}
+
+fn computed(value: Value) -> Output {
+    Output { value, path: None }
+}
+
+/// `..`: the input and everything below it, parents first. Iterative, so
+/// chains like `.. | ..` only use stack through `eval`.
+fn recurse(
+    input: Output,
+    emit: &mut dyn FnMut(Output) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut pending = vec![input];
+    while let Some(next) = pending.pop() {
+        let children: Vec<(Seg, Value)> = match &next.value {
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (Seg::Index(i), v.clone()))
+                .collect(),
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(k, v)| (Seg::Key(k.clone()), v.clone()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let base = next.path.clone();
+        emit(next)?;
+        for (seg, value) in children.into_iter().rev() {
+            let path = base.as_ref().map(|p| {
+                let mut p = p.clone();
+                p.push(seg);
+                p
+            });
+            pending.push(Output { value, path });
+        }
+    }
+    Ok(())
+}
+
+fn index(container: &Output, key: &Value) -> Result<Output, String> {
+    let extend = |seg: Seg| {
+        container.path.as_ref().map(|p| {
+            let mut p = p.clone();
+            p.push(seg);
+            p
+        })
+    };
+    match (&container.value, key) {
+        (Value::Object(fields), Value::String(k)) => Ok(Output {
+            value: fields.get(k).cloned().unwrap_or(Value::Null),
+            path: extend(Seg::Key(k.clone())),
+        }),
+        (Value::Null, Value::String(k)) => Ok(Output {
+            value: Value::Null,
+            path: extend(Seg::Key(k.clone())),
+        }),
+        (Value::Array(items), Value::Number(n)) => {
+            let i = n
+                .as_i64()
+                .ok_or_else(|| format!("array index must be an integer, got {}", n))?;
+            let resolved = if i < 0 { items.len() as i64 + i } else { i };
+            match usize::try_from(resolved).ok().filter(|&i| i < items.len()) {
+                Some(i) => Ok(Output {
+                    value: items[i].clone(),
+                    path: extend(Seg::Index(i)),
+                }),
+                None => Ok(computed(Value::Null)),
+            }
+        }
+        (Value::Null, Value::Number(_)) => Ok(computed(Value::Null)),
+        (value, key) => Err(format!(
+            "cannot index {} with {}",
+            type_name(value),
+            match key {
+                Value::String(s) => format!("\"{}\"", s),
+                other => type_name(other).to_string(),
+            }
+        )),
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// jq's total order: null < false < true < numbers < strings < arrays <
+/// objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (
+                x.as_f64().unwrap_or_default(),
+                y.as_f64().unwrap_or_default(),
+            );
+            x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(x, y))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        (Value::Object(x), Value::Object(y)) => {
+            let keys = |m: &serde_json::Map<String, Value>| m.keys().cloned().collect::<Vec<_>>();
+            let (kx, ky) = (keys(x), keys(y));
+            match kx.cmp(&ky) {
+                Ordering::Equal => kx
+                    .iter()
+                    .map(|k| compare(&x[k], &y[k]))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal),
+                other => other,
+            }
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    DotDot,
+    /// `.name`
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(Value),
+    Op(CmpOp),
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Pipe,
+    Comma,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let ident_start = |c: char| c.is_alphabetic() || c == '_';
+    let ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '.' if chars.get(i + 1) == Some(&'.') => {
+                tokens.push(Token::DotDot);
+                i += 2;
+            }
+            '.' if chars.get(i + 1).is_some_and(|&c| ident_start(c)) => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && ident_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Field(chars[start..i].iter().collect()));
+            }
+            '.' => {
+                tokens.push(Token::Dot);
+                i += 1;
+            }
+            '"' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                if i >= chars.len() {
+                    return Err("unterminated string".into());
+                }
+                i += 1;
+                let raw: String = chars[start..i].iter().collect();
+                let value: String =
+                    serde_json::from_str(&raw).map_err(|e| format!("bad string {}: {}", raw, e))?;
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())) =>
+            {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || ".eE+-".contains(chars[i])) {
+                    // `1-2` is not supported; a sign only follows an exponent.
+                    if "+-".contains(chars[i]) && !"eE".contains(chars[i - 1]) {
+                        break;
+                    }
+                    i += 1;
+                }
+                let raw: String = chars[start..i].iter().collect();
+                let value: Value =
+                    serde_json::from_str(&raw).map_err(|_| format!("bad number: {}", raw))?;
+                tokens.push(Token::Num(value));
+            }
+            c if ident_start(c) => {
+                let start = i;
+                while i < chars.len() && ident_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            '=' | '!' | '<' | '>' => {
+                let next_eq = chars.get(i + 1) == Some(&'=');
+                let op = match (c, next_eq) {
+                    ('=', true) => CmpOp::Eq,
+                    ('!', true) => CmpOp::Ne,
+                    ('<', true) => CmpOp::Le,
+                    ('>', true) => CmpOp::Ge,
+                    ('<', false) => CmpOp::Lt,
+                    ('>', false) => CmpOp::Gt,
+                    _ => return Err(format!("unexpected '{}' at {}", c, i)),
+                };
+                tokens.push(Token::Op(op));
+                i += if next_eq { 2 } else { 1 };
+            }
+            '[' => push(&mut tokens, &mut i, Token::LBracket),
+            ']' => push(&mut tokens, &mut i, Token::RBracket),
+            '(' => push(&mut tokens, &mut i, Token::LParen),
+            ')' => push(&mut tokens, &mut i, Token::RParen),
+            '|' => push(&mut tokens, &mut i, Token::Pipe),
+            ',' => push(&mut tokens, &mut i, Token::Comma),
+            other => return Err(format!("unexpected '{}' at {}", other, i)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn push(tokens: &mut Vec<Token>, i: &mut usize, token: Token) {
+    tokens.push(token);
+    *i += 1;
+}
+
+fn parse(src: &str) -> Result<Filter, String> {
+    let tokens = tokenize(src)?;
+    if tokens.is_empty() {
+        return Err("empty filter".into());
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+    };
+    let filter = parser.pipe()?;
+    match parser.peek() {
+        None => Ok(filter),
+        Some(token) => Err(format!("unexpected {:?}", token)),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Nesting plus chain length so far; see `MAX_DEPTH`.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            Err(format!("expected {:?}, found {:?}", token, self.peek()))
+        }
+    }
+
+    fn deeper(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("filter nested too deeply (max {})", MAX_DEPTH));
+        }
+        Ok(())
+    }
+
+    fn pipe(&mut self) -> Result<Filter, String> {
+        let outer = self.depth;
+        self.deeper()?;
+        let mut left = self.comma()?;
+        while self.eat(&Token::Pipe) {
+            self.deeper()?;
+            left = Filter::Pipe(Box::new(left), Box::new(self.comma()?));
+        }
+        self.depth = outer;
+        Ok(left)
+    }
+
+    fn comma(&mut self) -> Result<Filter, String> {
+        let outer = self.depth;
+        let mut left = self.or()?;
+        while self.eat(&Token::Comma) {
+            self.deeper()?;
+            left = Filter::Comma(Box::new(left), Box::new(self.or()?));
+        }
+        self.depth = outer;
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let outer = self.depth;
+        let mut left = self.and()?;
+        while self.eat(&Token::Ident("or".into())) {
+            self.deeper()?;
+            left = Filter::Or(Box::new(left), Box::new(self.and()?));
+        }
+        self.depth = outer;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let outer = self.depth;
+        let mut left = self.comparison()?;
+        while self.eat(&Token::Ident("and".into())) {
+            self.deeper()?;
+            left = Filter::And(Box::new(left), Box::new(self.comparison()?));
+        }
+        self.depth = outer;
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Filter, String> {
+        let left = self.postfix()?;
+        if let Some(Token::Op(op)) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.postfix()?;
+            return Ok(Filter::Compare(op, Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn postfix(&mut self) -> Result<Filter, String> {
+        let outer = self.depth;
+        let mut term = self.term()?;
+        loop {
+            if matches!(
+                self.peek(),
+                Some(Token::Field(_) | Token::Dot | Token::LBracket)
+            ) {
+                self.deeper()?;
+            }
+            match self.peek().cloned() {
+                Some(Token::Field(name)) => {
+                    self.pos += 1;
+                    term = field(term, name);
+                }
+                Some(Token::Dot)
+                    if matches!(self.tokens.get(self.pos + 1), Some(Token::Str(_))) =>
+                {
+                    self.pos += 1;
+                    let Some(Token::Str(name)) = self.next() else {
+                        unreachable!()
+                    };
+                    term = field(term, name);
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    term = self.bracket(term)?;
+                }
+                _ => {
+                    self.depth = outer;
+                    return Ok(term);
+                }
+            }
+        }
+    }
+
+    /// After `[`: `[]` iterates, `[expr]` indexes.
+    fn bracket(&mut self, target: Filter) -> Result<Filter, String> {
+        if self.eat(&Token::RBracket) {
+            return Ok(Filter::Iterate(Box::new(target)));
+        }
+        let key = self.pipe()?;
+        self.expect(Token::RBracket)?;
+        Ok(Filter::Index(Box::new(target), Box::new(key)))
+    }
+
+    fn term(&mut self) -> Result<Filter, String> {
+        match self.next() {
+            Some(Token::Field(name)) => Ok(field(Filter::Identity, name)),
+            Some(Token::Dot) => match self.peek() {
+                Some(Token::Str(_)) => {
+                    let Some(Token::Str(name)) = self.next() else {
+                        unreachable!()
+                    };
+                    Ok(field(Filter::Identity, name))
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    self.bracket(Filter::Identity)
+                }
+                _ => Ok(Filter::Identity),
+            },
+            Some(Token::DotDot) => Ok(Filter::Recurse),
+            Some(Token::Str(s)) => Ok(Filter::Literal(Value::String(s))),
+            Some(Token::Num(n)) => Ok(Filter::Literal(n)),
+            Some(Token::LParen) => {
+                let inner = self.pipe()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::LBracket) => {
+                if self.eat(&Token::RBracket) {
+                    return Ok(Filter::Literal(Value::Array(Vec::new())));
+                }
+                let inner = self.pipe()?;
+                self.expect(Token::RBracket)?;
+                Ok(Filter::Collect(Box::new(inner)))
+            }
+            Some(Token::Ident(name)) => self.builtin(&name),
+            other => Err(format!("unexpected {:?}", other)),
+        }
+    }
+
+    fn builtin(&mut self, name: &str) -> Result<Filter, String> {
+        let filter = match name {
+            "true" => Filter::Literal(Value::Bool(true)),
+            "false" => Filter::Literal(Value::Bool(false)),
+            "null" => Filter::Literal(Value::Null),
+            "keys" => Filter::Keys,
+            "length" => Filter::Length,
+            "type" => Filter::Type,
+            "not" => Filter::Not,
+            "select" => Filter::Select(Box::new(self.argument(name)?)),
+            "map" => Filter::Map(Box::new(self.argument(name)?)),
+            "has" => Filter::Has(Box::new(self.argument(name)?)),
+            other => return Err(format!("unsupported function: {}", other)),
+        };
+        Ok(filter)
+    }
+
+    fn argument(&mut self, name: &str) -> Result<Filter, String> {
+        if !self.eat(&Token::LParen) {
+            return Err(format!("{} takes one argument", name));
+        }
+        let arg = self.pipe()?;
+        self.expect(Token::RParen)?;
+        Ok(arg)
+    }
+}
+
+fn field(target: Filter, name: String) -> Filter {
+    Filter::Index(
+        Box::new(target),
+        Box::new(Filter::Literal(Value::String(name))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn values(filter: &str, input: Value) -> Vec<Value> {
+        run(filter, &input)
+            .unwrap()
+            .into_iter()
+            .map(|o| o.value)
+            .collect()
+    }
+
+    #[test]
+    fn paths_follow_the_input() {
+        let doc = json!({ "items": [{ "n": 1, "ok": true }, { "n": 5, "ok": false }] });
+        let out = run(".items[] | select(.n > 2 and (.ok | not)) | .n", &doc).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].value, json!(5));
+        assert_eq!(
+            out[0].path,
+            Some(vec![
+                Seg::Key("items".into()),
+                Seg::Index(1),
+                Seg::Key("n".into())
+            ])
+        );
+
+        let out = run(".items | length", &doc).unwrap();
+        assert_eq!(
+            (out[0].value.clone(), out[0].path.clone()),
+            (json!(2), None)
+        );
+    }
+
+    #[test]
+    fn builtins() {
+        let doc = json!({ "b": [3, 1], "a": { "x": null } });
+        assert_eq!(values("keys", doc.clone()), [json!(["a", "b"])]);
+        assert_eq!(
+            values(".b | map(. > 2)", doc.clone()),
+            [json!([true, false])]
+        );
+        assert_eq!(
+            values("[.b[] | select(. >= 2)], .b[-1]", doc.clone()),
+            [json!([3]), json!(1)]
+        );
+        assert_eq!(
+            values(".a | has(\"x\"), (.x | type)", doc.clone()),
+            [json!(true), json!("null")]
+        );
+        assert_eq!(values("[..] | length", doc.clone()), [json!(6)]);
+        assert_eq!(values(".\"a\".missing.deeper", doc), [Value::Null]);
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert!(run(".a[]", &json!({ "a": 1 }))
+            .unwrap_err()
+            .contains("cannot iterate"));
+        assert!(run("sort", &json!([]))
+            .unwrap_err()
+            .contains("unsupported function"));
+        assert!(run(".a |", &json!({})).is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let doc = json!({ "a": 1 });
+        let nested = |n: usize| format!("{}.{}", "(".repeat(n), ")".repeat(n));
+        assert!(run(&nested(1000), &doc)
+            .unwrap_err()
+            .contains("nested too deeply"));
+        assert!(run(&vec![".a"; 10_000].join(" | "), &doc)
+            .unwrap_err()
+            .contains("nested too deeply"));
+        assert!(run(&format!("{}.{}", "[".repeat(500), "]".repeat(500)), &doc).is_err());
+
+        // Right at the limits, parsing and evaluation stay within the stack
+        // of a test thread, which is no larger than a tokio worker's.
+        assert_eq!(values(&nested(MAX_DEPTH - 1), doc.clone()), [json!({ "a": 1 })]);
+        let chain = vec!["."; MAX_DEPTH - 1].join(" | ");
+        assert_eq!(values(&chain, doc.clone()), [json!({ "a": 1 })]);
+        let mixed = format!("{}.a{}", "(. | ".repeat(30), ")".repeat(30));
+        assert_eq!(values(&mixed, doc), [json!(1)]);
+
+        // Nested `map`s use several `eval` frames per level; on a deep
+        // document the run-time limit stops them before the stack runs out.
+        let mut deep = json!(1);
+        for _ in 0..120 {
+            deep = json!([deep]);
+        }
+        let maps = format!(
+            "{}..{}",
+            "map(".repeat(MAX_DEPTH - 1),
+            ")".repeat(MAX_DEPTH - 1)
+        );
+        assert!(run(&maps, &deep).unwrap_err().contains("nested too deeply"));
+    }
+}
@@ -0,0 +1,208 @@
+//! Queries a JSON document with JSONPath (RFC 9535) or a jq subset and
+//! returns each match with its location.
+
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::{JsonPath, PathElement};
+
+use super::json_format::parse;
+use crate::error::{ApiError, ApiResult};
+
+mod jq;
+
+/// Bracket / parenthesis nesting allowed in a JSONPath query. Deeper
+/// queries overflow the stack inside `serde_json_path`, and nested filters
+/// take time exponential in their depth.
+const MAX_JSONPATH_DEPTH: usize = 16;
+
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Language {
+    #[default]
+    JsonPath,
+    Jq,
+}
+
+#[derive(Deserialize)]
+pub(super) struct JsonQueryReq {
+    /// The document, as JSON text.
+    json: String,
+    query: String,
+    #[serde(default)]
+    language: Language,
+}
+
+#[derive(Serialize)]
+pub(super) struct JsonQueryResp {
+    language: Language,
+    count: usize,
+    matches: Vec<Match>,
+}
+
+#[derive(Serialize)]
+struct Match {
+    /// Normalized JSONPath (`$['a'][0]`); `None` for computed jq values
+    /// such as `length` or `[...]`.
+    path: Option<String>,
+    /// The same location as a JSON Pointer (`/a/0`).
+    pointer: Option<String>,
+    value: Value,
+}
+
+/// One step of a location in the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Seg {
+    Key(String),
+    Index(usize),
+}
+
+pub async fn handle(Json(req): Json<JsonQueryReq>) -> ApiResult<JsonQueryResp> {
+    let document = parse(&req.json, "json")?;
+    let query = req.query.trim();
+    if query.is_empty() {
+        return Err(ApiError::BadRequest("empty query".into()));
+    }
+
+    let matches: Vec<Match> = match req.language {
+        Language::JsonPath => {
+            if jsonpath_depth(query) > MAX_JSONPATH_DEPTH {
+                return Err(ApiError::BadRequest(format!(
+                    "invalid jsonpath: nested too deeply (max {})",
+                    MAX_JSONPATH_DEPTH
+                )));
+            }
+            let path = JsonPath::parse(query)
+                .map_err(|e| ApiError::BadRequest(format!("invalid jsonpath: {}", e)))?;
+            path.query_located(&document)
+                .into_iter()
+                .map(|node| {
+                    let segs: Vec<Seg> = node
+                        .location()
+                        .iter()
+                        .map(|element| match element {
+                            PathElement::Name(name) => Seg::Key(name.to_string()),
+                            PathElement::Index(i) => Seg::Index(*i),
+                        })
+                        .collect();
+                    located(Some(&segs), node.node().clone())
+                })
+                .collect()
+        }
+        Language::Jq => jq::run(query, &document)
+            .map_err(|e| ApiError::BadRequest(format!("jq: {}", e)))?
+            .into_iter()
+            .map(|out| located(out.path.as_deref(), out.value))
+            .collect(),
+    };
+    Ok(Json(JsonQueryResp {
+        language: req.language,
+        count: matches.len(),
+        matches,
+    }))
+}
+
+/// Deepest nesting of `[` and `(` outside string literals.
+fn jsonpath_depth(query: &str) -> usize {
+    let (mut depth, mut max) = (0usize, 0);
+    let mut quote = None;
+    let mut chars = query.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(') => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            (None, ']' | ')') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max
+}
+
+fn located(path: Option<&[Seg]>, value: Value) -> Match {
+    Match {
+        path: path.map(normalized_path),
+        pointer: path.map(json_pointer),
+        value,
+    }
+}
+
+/// RFC 9535 normalized path: `$['name'][0]`, with `'` and `\` escaped.
+fn normalized_path(segs: &[Seg]) -> String {
+    let mut out = String::from("$");
+    for seg in segs {
+        match seg {
+            Seg::Key(key) => {
+                out.push_str("['");
+                for c in key.chars() {
+                    match c {
+                        '\'' => out.push_str("\\'"),
+                        '\\' => out.push_str("\\\\"),
+                        c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                        c => out.push(c),
+                    }
+                }
+                out.push_str("']");
+            }
+            Seg::Index(i) => out.push_str(&format!("[{}]", i)),
+        }
+    }
+    out
+}
+
+/// RFC 6901 JSON Pointer; empty for the root.
+fn json_pointer(segs: &[Seg]) -> String {
+    segs.iter()
+        .map(|seg| match seg {
+            Seg::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+            Seg::Index(i) => format!("/{}", i),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    async fn query(query: String, language: Language) -> ApiResult<JsonQueryResp> {
+        handle(Json(JsonQueryReq {
+            json: json!({ "a": [1, 2] }).to_string(),
+            query,
+            language,
+        }))
+        .await
+    }
+
+    #[tokio::test]
+    async fn deep_nesting_is_rejected() {
+        let parens = format!("$[?{}@.a{}]", "(".repeat(300), ")".repeat(300));
+        assert!(matches!(
+            query(parens, Language::JsonPath).await,
+            Err(ApiError::BadRequest(m)) if m.contains("nested too deeply")
+        ));
+        let jq = format!("{}.{}", "(".repeat(1000), ")".repeat(1000));
+        assert!(matches!(
+            query(jq, Language::Jq).await,
+            Err(ApiError::BadRequest(m)) if m.contains("nested too deeply")
+        ));
+
+        // Brackets inside string literals do not count.
+        let quoted = format!("$['{}']", "[(".repeat(50));
+        assert_eq!(jsonpath_depth(&quoted), 1);
+        let deepest = format!(
+            "$[?{}@.a{}]",
+            "(".repeat(MAX_JSONPATH_DEPTH - 1),
+            ")".repeat(MAX_JSONPATH_DEPTH - 1)
+        );
+        assert!(query(deepest, Language::JsonPath).await.is_ok());
+    }
+}
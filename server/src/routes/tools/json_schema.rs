@@ -0,0 +1,419 @@
+//! JSON Schema validation and schema inference. Remote `$ref`s are not
+//! fetched: the validator is built without the HTTP and file resolvers, so
+//! a schema can only refer to itself.
+
+use std::str::FromStr;
+
+use axum::Json;
+use jsonschema::{paths::LocationSegment, Draft};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::json_format::parse;
+use crate::error::{ApiError, ApiResult};
+
+/// Drafts a caller can ask for; `$schema` in the schema picks one too.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum SchemaDraft {
+    Draft7,
+    Draft201909,
+    Draft202012,
+}
+
+impl SchemaDraft {
+    fn as_str(self) -> &'static str {
+        match self {
+            SchemaDraft::Draft7 => "draft7",
+            SchemaDraft::Draft201909 => "2019-09",
+            SchemaDraft::Draft202012 => "2020-12",
+        }
+    }
+
+    fn uri(self) -> &'static str {
+        match self {
+            SchemaDraft::Draft7 => "http://json-schema.org/draft-07/schema#",
+            SchemaDraft::Draft201909 => "https://json-schema.org/draft/2019-09/schema",
+            SchemaDraft::Draft202012 => "https://json-schema.org/draft/2020-12/schema",
+        }
+    }
+
+    fn draft(self) -> Draft {
+        match self {
+            SchemaDraft::Draft7 => Draft::Draft7,
+            SchemaDraft::Draft201909 => Draft::Draft201909,
+            SchemaDraft::Draft202012 => Draft::Draft202012,
+        }
+    }
+
+    /// Reads `$schema`; `None` when it is missing or names another draft.
+    fn declared(schema: &Value) -> Option<Self> {
+        let uri = schema.get("$schema")?.as_str()?.trim_end_matches('#');
+        [
+            SchemaDraft::Draft7,
+            SchemaDraft::Draft201909,
+            SchemaDraft::Draft202012,
+        ]
+        .into_iter()
+        .find(|d| d.uri().trim_end_matches('#') == uri)
+    }
+}
+
+impl FromStr for SchemaDraft {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "7" | "draft7" | "draft-07" => Ok(SchemaDraft::Draft7),
+            "2019-09" | "draft2019-09" => Ok(SchemaDraft::Draft201909),
+            "2020-12" | "draft2020-12" => Ok(SchemaDraft::Draft202012),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ValidateReq {
+    /// The document to check, as JSON text.
+    json: String,
+    /// The schema, as JSON text.
+    schema: String,
+    /// Overrides `$schema`; defaults to 2020-12 when neither is given.
+    #[serde(default)]
+    draft: Option<String>,
+    /// Treat `format` as an assertion. On by default for draft 7 only, as
+    /// the later drafts make it an annotation.
+    #[serde(default)]
+    validate_formats: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub(super) struct ValidateResp {
+    valid: bool,
+    draft: &'static str,
+    errors: Vec<Violation>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Violation {
+    /// JSON Pointer into the document; empty for the root.
+    path: String,
+    /// The schema keyword that failed, e.g. `type` or `required`.
+    keyword: String,
+    /// JSON Pointer to that keyword in the schema.
+    schema_path: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct InferReq {
+    /// Example document, as JSON text.
+    json: String,
+    /// Draft named in `$schema`; 2020-12 by default.
+    #[serde(default)]
+    draft: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct InferResp {
+    draft: &'static str,
+    schema: Value,
+}
+
+pub async fn handle_validate(Json(req): Json<ValidateReq>) -> ApiResult<ValidateResp> {
+    let document = parse(&req.json, "json")?;
+    let schema = parse(&req.schema, "schema")?;
+    let draft = match req.draft.as_deref() {
+        Some(name) => parse_draft(name)?,
+        None => SchemaDraft::declared(&schema).unwrap_or(SchemaDraft::Draft202012),
+    };
+
+    let mut options = jsonschema::options().with_draft(draft.draft());
+    if let Some(yes) = req.validate_formats {
+        options = options.should_validate_formats(yes);
+    }
+    let validator = options
+        .build(&schema)
+        .map_err(|e| ApiError::BadRequest(format!("invalid schema: {}", e)))?;
+
+    let errors: Vec<Violation> = validator
+        .iter_errors(&document)
+        .map(|err| Violation {
+            path: err.instance_path.as_str().to_string(),
+            keyword: keyword(&err.schema_path),
+            schema_path: err.schema_path.as_str().to_string(),
+            message: err.to_string(),
+        })
+        .collect();
+    Ok(Json(ValidateResp {
+        valid: errors.is_empty(),
+        draft: draft.as_str(),
+        errors,
+    }))
+}
+
+pub async fn handle_infer(Json(req): Json<InferReq>) -> ApiResult<InferResp> {
+    let document = parse(&req.json, "json")?;
+    let draft = match req.draft.as_deref() {
+        Some(name) => parse_draft(name)?,
+        None => SchemaDraft::Draft202012,
+    };
+
+    let mut shape = Shape::default();
+    shape.add(&document);
+    let mut schema = Map::new();
+    schema.insert("$schema".into(), json!(draft.uri()));
+    if let Value::Object(body) = shape.to_schema() {
+        schema.extend(body);
+    }
+    Ok(Json(InferResp {
+        draft: draft.as_str(),
+        schema: Value::Object(schema),
+    }))
+}
+
+fn parse_draft(name: &str) -> Result<SchemaDraft, ApiError> {
+    SchemaDraft::from_str(name).map_err(|_| {
+        ApiError::BadRequest(format!(
+            "unsupported draft: {} (expected draft7, 2019-09 or 2020-12)",
+            name.trim()
+        ))
+    })
+}
+
+/// The last keyword in a schema path; indices (`anyOf/1`) are skipped.
+fn keyword(schema_path: &jsonschema::paths::Location) -> String {
+    schema_path
+        .into_iter()
+        .rev()
+        .find_map(|segment| match segment {
+            LocationSegment::Property(name) => Some(name.to_string()),
+            LocationSegment::Index(_) => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Everything seen at one position of the example: every type that
+/// occurred, with object properties and array items merged across
+/// occurrences.
+#[derive(Default)]
+struct Shape {
+    null: bool,
+    boolean: bool,
+    integer: bool,
+    number: bool,
+    string: Option<StringShape>,
+    /// Merged shape of the elements of every array seen here.
+    array: Option<Box<Shape>>,
+    object: Option<ObjectShape>,
+}
+
+struct StringShape {
+    /// Kept only while every string seen has the same format.
+    format: Option<&'static str>,
+}
+
+#[derive(Default)]
+struct ObjectShape {
+    /// Objects seen; a property is required when it was in all of them.
+    seen: usize,
+    /// In order of first appearance, with the number of objects having it.
+    properties: Vec<(String, usize, Shape)>,
+}
+
+impl Shape {
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(n) if n.is_i64() || n.is_u64() => self.integer = true,
+            Value::Number(_) => self.number = true,
+            Value::String(s) => {
+                let format = string_format(s);
+                match &mut self.string {
+                    Some(shape) if shape.format != format => shape.format = None,
+                    Some(_) => {}
+                    None => self.string = Some(StringShape { format }),
+                }
+            }
+            Value::Array(items) => {
+                let shape = self.array.get_or_insert_with(Default::default);
+                for item in items {
+                    shape.add(item);
+                }
+            }
+            Value::Object(fields) => {
+                let shape = self.object.get_or_insert_with(Default::default);
+                shape.seen += 1;
+                for (name, value) in fields {
+                    match shape.properties.iter_mut().find(|(n, _, _)| n == name) {
+                        Some((_, count, property)) => {
+                            *count += 1;
+                            property.add(value);
+                        }
+                        None => {
+                            let mut property = Shape::default();
+                            property.add(value);
+                            shape.properties.push((name.clone(), 1, property));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// `{}` when nothing was seen, e.g. the items of an empty array.
+    fn to_schema(&self) -> Value {
+        let mut types: Vec<&str> = Vec::new();
+        let mut schema = Map::new();
+        if self.null {
+            types.push("null");
+        }
+        if self.boolean {
+            types.push("boolean");
+        }
+        // Integers and fractions at the same place make a number.
+        if self.number {
+            types.push("number");
+        } else if self.integer {
+            types.push("integer");
+        }
+        if let Some(string) = &self.string {
+            types.push("string");
+            if let Some(format) = string.format {
+                schema.insert("format".into(), json!(format));
+            }
+        }
+        if let Some(items) = &self.array {
+            types.push("array");
+            let items = items.to_schema();
+            if items.as_object().is_some_and(|m| !m.is_empty()) {
+                schema.insert("items".into(), items);
+            }
+        }
+        if let Some(object) = &self.object {
+            types.push("object");
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            for (name, count, property) in &object.properties {
+                properties.insert(name.clone(), property.to_schema());
+                if *count == object.seen {
+                    required.push(json!(name));
+                }
+            }
+            schema.insert("properties".into(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".into(), Value::Array(required));
+            }
+        }
+
+        match types.as_slice() {
+            [] => {}
+            [one] => {
+                schema.insert("type".into(), json!(one));
+            }
+            many => {
+                schema.insert("type".into(), json!(many));
+            }
+        }
+        Value::Object(schema)
+    }
+}
+
+/// Formats worth asserting on, recognised from the example value.
+fn string_format(s: &str) -> Option<&'static str> {
+    use time::format_description::well_known::Rfc3339;
+
+    if time::OffsetDateTime::parse(s, &Rfc3339).is_ok() {
+        return Some("date-time");
+    }
+    let date = time::macros::format_description!("[year]-[month]-[day]");
+    if time::Date::parse(s, &date).is_ok() {
+        return Some("date");
+    }
+    if s.len() == 36 && uuid::Uuid::parse_str(s).is_ok() {
+        return Some("uuid");
+    }
+    let looks_like_email = s.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty()
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && !s.contains(char::is_whitespace)
+    });
+    looks_like_email.then_some("email")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(document: Value) -> Value {
+        let mut shape = Shape::default();
+        shape.add(&document);
+        shape.to_schema()
+    }
+
+    #[test]
+    fn infers_merged_array_items() {
+        let schema = infer(json!([
+            { "id": 1, "email": "a@example.com", "tags": [] },
+            { "id": 2.5, "email": "b@example.com", "note": null },
+        ]));
+        assert_eq!(
+            schema,
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "number" },
+                        "email": { "type": "string", "format": "email" },
+                        "tags": { "type": "array" },
+                        "note": { "type": "null" },
+                    },
+                    "required": ["email", "id"],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn mixed_types_and_formats() {
+        let schema = infer(json!(["2024-01-02", "2024-01-02T03:04:05Z", null]));
+        assert_eq!(schema["items"], json!({ "type": ["null", "string"] }));
+    }
+
+    #[tokio::test]
+    async fn reports_every_violation() {
+        let req = ValidateReq {
+            json: r#"{ "name": 3, "tags": ["a", 1] }"#.into(),
+            schema: r#"{
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "required": ["name", "id"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "tags": { "type": "array", "items": { "type": "string" } }
+                }
+            }"#
+            .into(),
+            draft: None,
+            validate_formats: None,
+        };
+        let Json(resp) = handle_validate(Json(req)).await.unwrap();
+        assert!(!resp.valid);
+        assert_eq!(resp.draft, "draft7");
+        let mut found: Vec<(&str, &str)> = resp
+            .errors
+            .iter()
+            .map(|e| (e.path.as_str(), e.keyword.as_str()))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [("", "required"), ("/name", "type"), ("/tags/1", "type")]
+        );
+    }
+}
@@ -1,18 +1,81 @@
-use axum::{routing::post, Router};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{get, post},
+    Router,
+};
 
 use crate::app::AppState;
 
 mod base64;
+mod detect;
 mod hash;
+mod json_diff;
 mod json_format;
+mod json_query;
+mod json_schema;
 mod timestamp;
 mod translate;
 
-pub fn router() -> Router<AppState> {
+pub(crate) use translate::{cache_key, cache_prefix, CacheScope, Format};
+
+/// Upper bound for an imported TMX file.
+const TMX_MAX_BYTES: usize = 64 * 1024 * 1024;
+/// Room for the multipart framing and the small fields next to a document
+/// upload.
+const MULTIPART_SLACK_BYTES: usize = 64 * 1024;
+
+/// `document_max_bytes` is `DOCUMENT_MAX_BYTES`; the handler also checks the
+/// file part against it.
+pub fn router(document_max_bytes: usize) -> Router<AppState> {
     Router::new()
         .route("/api/tools/json/format", post(json_format::handle))
+        .route("/api/tools/json/diff", post(json_diff::handle_diff))
+        .route("/api/tools/json/patch", post(json_diff::handle_patch))
+        .route("/api/tools/json/query", post(json_query::handle))
+        .route(
+            "/api/tools/json/validate",
+            post(json_schema::handle_validate),
+        )
+        .route("/api/tools/json/schema", post(json_schema::handle_infer))
         .route("/api/tools/hash", post(hash::handle))
         .route("/api/tools/base64", post(base64::handle))
         .route("/api/tools/timestamp", post(timestamp::handle))
         .route("/api/tools/translate", post(translate::handle))
+        .route(
+            "/api/tools/translate/stream",
+            get(translate::handle_stream_get).post(translate::handle_stream_post),
+        )
+        .route("/api/tools/translate/batch", post(translate::handle_batch))
+        .route(
+            "/api/tools/translate/documents",
+            post(translate::handle_document).layer(DefaultBodyLimit::max(
+                document_max_bytes.saturating_add(MULTIPART_SLACK_BYTES),
+            )),
+        )
+        .route(
+            "/api/tools/translate/documents/:id",
+            get(translate::document_status),
+        )
+        .route(
+            "/api/tools/translate/documents/:id/result",
+            get(translate::document_result),
+        )
+        .route(
+            "/api/tools/translate/memory/lookup",
+            post(translate::memory_lookup),
+        )
+        .route(
+            "/api/tools/translate/memory/import",
+            post(translate::memory_import).layer(DefaultBodyLimit::max(TMX_MAX_BYTES)),
+        )
+        .route(
+            "/api/tools/translate/memory/export",
+            get(translate::memory_export),
+        )
+        .route("/api/tools/translate/languages", get(translate::languages))
+        .route(
+            "/api/tools/translate/glossaries",
+            get(translate::glossaries),
+        )
+        .route("/api/tools/detect", post(detect::handle))
 }
@@ -33,33 +33,23 @@ pub(super) struct TimestampResp {
     pub zone_offset: String,
 }
 
-#[derive(Clone, Copy, Deserialize)]
+#[derive(Clone, Copy, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) enum TimestampMode {
+    #[default]
     Now,
     FromUnix,
     FromIso,
 }
 
-impl Default for TimestampMode {
-    fn default() -> Self {
-        TimestampMode::Now
-    }
-}
-
-#[derive(Clone, Copy, Deserialize)]
+#[derive(Clone, Copy, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) enum TimestampUnit {
+    #[default]
     Seconds,
     Milliseconds,
 }
 
-impl Default for TimestampUnit {
-    fn default() -> Self {
-        TimestampUnit::Seconds
-    }
-}
-
 fn default_mode() -> TimestampMode {
     TimestampMode::Now
 }
@@ -136,7 +126,7 @@ fn parse_iso(input: Option<&str>, tz: Option<&str>) -> Result<OffsetDateTime, Ap
         .map_err(|_| ApiError::BadRequest("invalid datetime format".into()))?;
 
     let offset = tz
-        .and_then(|label| parse_timezone(label))
+        .and_then(parse_timezone)
         .or_else(local_offset)
         .unwrap_or(UtcOffset::UTC);
 
@@ -0,0 +1,176 @@
+//! Back-translation check: the translation is translated back into the
+//! source language and compared with the original segment by segment, so
+//! callers who cannot read the target language can spot drift. Both legs go
+//! through the provider chain and the translation cache.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::{
+    cached_translation, check_budget,
+    pack::{into_api_error, translate_misses, MissKey},
+    remember, CacheScope, Caller, Format, ResolvedLangs,
+};
+use crate::{
+    app::AppState, error::ApiError, markdown::Document, memory, providers::TranslateOptions,
+};
+
+/// Segments scoring below this are flagged, unless the request sets
+/// `backTranslateThreshold`.
+pub(super) const DEFAULT_THRESHOLD: f32 = 0.6;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct BackTranslation {
+    /// Mean of the segment scores, weighted by source length.
+    similarity: f32,
+    threshold: f32,
+    /// Number of flagged segments.
+    flagged: usize,
+    segments: Vec<Segment>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Segment {
+    source: String,
+    translation: String,
+    back_translation: String,
+    similarity: f32,
+    flagged: bool,
+}
+
+/// Translates `translation` back into the source language and scores each
+/// segment against `source`.
+pub(super) async fn back_translate(
+    state: &AppState,
+    caller: &Caller,
+    langs: ResolvedLangs,
+    format: Format,
+    source: &str,
+    translation: &str,
+    threshold: f32,
+) -> Result<BackTranslation, ApiError> {
+    let pairs = align(format, source, translation);
+    let reverse = ResolvedLangs {
+        source: langs.target,
+        confidence: None,
+        target: langs.source,
+    };
+
+    let mut back: HashMap<String, String> = HashMap::new();
+    let mut misses: Vec<MissKey> = Vec::new();
+    for (_, translated) in &pairs {
+        if back.contains_key(translated) || misses.iter().any(|m| m.text == *translated) {
+            continue;
+        }
+        match cached_translation(state, reverse, CacheScope::default(), translated).await {
+            Some((text, _)) => {
+                back.insert(translated.clone(), text);
+            }
+            None => misses.push(MissKey {
+                source: reverse.source,
+                target: reverse.target,
+                text: translated.clone(),
+            }),
+        }
+    }
+
+    if !misses.is_empty() {
+        check_budget(state, caller)?;
+    }
+    for (miss, outcome) in
+        translate_misses(state, caller, TranslateOptions::default(), misses).await
+    {
+        let result = outcome.map_err(into_api_error)?;
+        remember(
+            state,
+            miss.source,
+            miss.target,
+            CacheScope::default(),
+            &miss.text,
+            &result,
+        )
+        .await;
+        back.insert(miss.text, result.text);
+    }
+
+    let mut weighted = 0.0;
+    let mut total_len = 0;
+    let segments: Vec<Segment> = pairs
+        .into_iter()
+        .map(|(source, translation)| {
+            let back_translation = back.get(&translation).cloned().unwrap_or_default();
+            let similarity = score(&source, &back_translation);
+            let len = source.chars().count().max(1);
+            weighted += similarity * len as f32;
+            total_len += len;
+            Segment {
+                source,
+                translation,
+                back_translation,
+                similarity,
+                flagged: similarity < threshold,
+            }
+        })
+        .collect();
+
+    Ok(BackTranslation {
+        similarity: weighted / total_len.max(1) as f32,
+        threshold,
+        flagged: segments.iter().filter(|s| s.flagged).count(),
+        segments,
+    })
+}
+
+/// Pairs source and translation segments: non-empty lines for text, prose
+/// segments for Markdown. When the counts differ the whole text is one
+/// segment.
+fn align(format: Format, source: &str, translation: &str) -> Vec<(String, String)> {
+    let split = |text: &str| -> Vec<String> {
+        match format {
+            Format::Text => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Format::Markdown => Document::parse(text)
+                .segments()
+                .map(|segment| segment.trim().to_string())
+                .filter(|segment| !segment.is_empty())
+                .collect(),
+        }
+    };
+    let sources = split(source);
+    let translations = split(translation);
+    if sources.len() == translations.len() && !sources.is_empty() {
+        sources.into_iter().zip(translations).collect()
+    } else {
+        vec![(sources.join("\n"), translations.join("\n"))]
+    }
+}
+
+/// Edit-distance similarity (as in the translation memory) after folding
+/// case and dropping punctuation, which round trips rarely preserve.
+fn score(original: &str, round_trip: &str) -> f32 {
+    let a = normalize(original);
+    let b = normalize(round_trip);
+    memory::similarity(&a, &b)
+}
+
+fn normalize(text: &str) -> Vec<char> {
+    let mut out: Vec<char> = Vec::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            out.push(c);
+        } else if out.last().is_some_and(|last| *last != ' ') {
+            out.push(' ');
+        }
+    }
+    if out.last() == Some(&' ') {
+        out.pop();
+    }
+    out
+}
@@ -0,0 +1,178 @@
+use std::{collections::HashMap, fmt};
+
+use super::{
+    cached_translation, check_budget,
+    pack::{item_error, translate_misses, MissKey},
+    remember, resolve_langs, CacheScope, Caller,
+};
+use crate::{
+    app::AppState,
+    error::{ApiError, ApiErrorBody, ApiResult},
+    providers::Style,
+};
+use axum::{extract::State, Json};
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+const MAX_ITEMS: usize = 500;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(in crate::routes::tools) struct BatchReq {
+    texts: BatchTexts,
+    #[serde(default)]
+    source_lang: Option<String>,
+    #[serde(default)]
+    target_lang: Option<String>,
+    /// Applies to every text in the batch.
+    #[serde(default)]
+    style: Option<Style>,
+}
+
+/// Either `["a", "b"]` or `{"key": "a", ...}`; map order is preserved.
+struct BatchTexts(Vec<(Option<String>, String)>);
+
+impl<'de> Deserialize<'de> for BatchTexts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TextsVisitor;
+
+        impl<'de> Visitor<'de> for TextsVisitor {
+            type Value = BatchTexts;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an array of strings or a map of key to string")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut out = Vec::new();
+                while let Some(text) = seq.next_element::<String>()? {
+                    out.push((None, text));
+                }
+                Ok(BatchTexts(out))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut out = Vec::new();
+                while let Some((key, text)) = map.next_entry::<String, String>()? {
+                    out.push((Some(key), text));
+                }
+                Ok(BatchTexts(out))
+            }
+        }
+
+        deserializer.deserialize_any(TextsVisitor)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(in crate::routes::tools) struct BatchResp {
+    results: Vec<BatchItem>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchItem {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    detected_lang: Option<&'static str>,
+    target_lang: Option<&'static str>,
+    translation: Option<String>,
+    provider: Option<String>,
+    cached: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ApiErrorBody>,
+}
+
+pub async fn handle_batch(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<BatchReq>,
+) -> ApiResult<BatchResp> {
+    state.translator()?;
+    let entries = req.texts.0;
+    if entries.is_empty() {
+        return Err(ApiError::BadRequest("empty texts".into()));
+    }
+    if entries.len() > MAX_ITEMS {
+        return Err(ApiError::BadRequest(format!(
+            "too many texts: {} (max {})",
+            entries.len(),
+            MAX_ITEMS
+        )));
+    }
+
+    let scope = CacheScope {
+        style: req.style,
+        ..CacheScope::default()
+    };
+    let mut results = Vec::with_capacity(entries.len());
+    let mut misses: HashMap<MissKey, Vec<usize>> = HashMap::new();
+    let mut miss_order: Vec<MissKey> = Vec::new();
+
+    for (index, (key, raw)) in entries.into_iter().enumerate() {
+        let mut item = BatchItem {
+            key,
+            detected_lang: None,
+            target_lang: None,
+            translation: None,
+            provider: None,
+            cached: false,
+            error: None,
+        };
+        let text = raw.trim();
+        if text.is_empty() {
+            item.error = Some(item_error("BadRequest", "empty text"));
+            results.push(item);
+            continue;
+        }
+
+        let langs = resolve_langs(text, req.source_lang.as_deref(), req.target_lang.as_deref())?;
+        item.detected_lang = Some(langs.source.as_str());
+        item.target_lang = Some(langs.target.as_str());
+
+        if let Some((translation, provider)) = cached_translation(&state, langs, scope, text).await
+        {
+            item.translation = Some(translation);
+            item.provider = Some(provider);
+            item.cached = true;
+        } else {
+            let miss = MissKey {
+                source: langs.source,
+                target: langs.target,
+                text: text.to_string(),
+            };
+            let slots = misses.entry(miss.clone()).or_default();
+            if slots.is_empty() {
+                miss_order.push(miss);
+            }
+            slots.push(index);
+        }
+        results.push(item);
+    }
+
+    if !miss_order.is_empty() {
+        check_budget(&state, &caller)?;
+    }
+    for (miss, outcome) in translate_misses(&state, &caller, scope.options(), miss_order).await {
+        let slots = misses.remove(&miss).unwrap_or_default();
+        match outcome {
+            Ok(result) => {
+                remember(&state, miss.source, miss.target, scope, &miss.text, &result).await;
+                for index in slots {
+                    results[index].translation = Some(result.text.clone());
+                    results[index].provider = Some(result.provider.clone());
+                }
+            }
+            Err(err) => {
+                for index in slots {
+                    results[index].error = Some(item_error(err.code, &err.message));
+                }
+            }
+        }
+    }
+
+    Ok(Json(BatchResp { results }))
+}
@@ -0,0 +1,368 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use axum::{
+    extract::{multipart::Field, Multipart, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tracing::{error, info};
+
+use super::{
+    cached_translation, check_budget,
+    pack::{translate_misses_with_progress, MissKey, PACK_MAX_CHARS},
+    remember, resolve_langs, CacheScope, Caller, ResolvedLangs,
+};
+use crate::{
+    app::AppState,
+    documents::{self, DocFormat, Translatable},
+    error::{ApiError, ApiResult},
+    jobs::{Job, JobStatus, TooManyJobs},
+    providers::Style,
+};
+
+/// Units longer than this are split so every piece fits in one pack.
+const PIECE_MAX_CHARS: usize = PACK_MAX_CHARS * 3 / 4;
+/// Text sampled from the start of a document for language detection.
+const DETECT_SAMPLE_CHARS: usize = 2000;
+/// Bound for the non-file parts (`sourceLang`, `targetLang`, `style`).
+const TEXT_FIELD_MAX_BYTES: usize = 256;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(in crate::routes::tools) struct JobResp {
+    id: String,
+    filename: String,
+    format: &'static str,
+    status: JobStatus,
+    detected_lang: &'static str,
+    target_lang: &'static str,
+    progress: Progress,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Progress {
+    done: usize,
+    total: usize,
+}
+
+impl From<&Job> for JobResp {
+    fn from(job: &Job) -> Self {
+        Self {
+            id: job.id.clone(),
+            filename: job.filename.clone(),
+            format: job.format.as_str(),
+            status: job.status,
+            detected_lang: job.source.as_str(),
+            target_lang: job.target.as_str(),
+            progress: Progress {
+                done: job.done,
+                total: job.total,
+            },
+            error: job.error.clone(),
+            result_url: (job.status == JobStatus::Done)
+                .then(|| format!("/api/tools/translate/documents/{}/result", job.id)),
+        }
+    }
+}
+
+/// `POST /api/tools/translate/documents`, multipart with a `file` part and
+/// optional `sourceLang` / `targetLang` / `style` fields. The file is parsed up front,
+/// so unsupported or malformed uploads fail immediately; translation runs
+/// in the background.
+pub async fn handle_document(
+    State(state): State<AppState>,
+    caller: Caller,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<JobResp>), ApiError> {
+    state.translator()?;
+    let mut file: Option<(String, Vec<u8>)> = None;
+    let mut source_lang: Option<String> = None;
+    let mut target_lang: Option<String> = None;
+    let mut style: Option<Style> = None;
+
+    while let Some(mut field) = multipart.next_field().await.map_err(bad_multipart)? {
+        let name = field.name().unwrap_or_default().to_string();
+        match name.as_str() {
+            "file" => {
+                let filename = field
+                    .file_name()
+                    .map(str::to_string)
+                    .ok_or_else(|| ApiError::BadRequest("file part has no filename".into()))?;
+                let bytes = read_field(&mut field, state.jobs.max_bytes)
+                    .await?
+                    .ok_or_else(|| {
+                        ApiError::BadRequest(format!(
+                            "file too large (max {} bytes)",
+                            state.jobs.max_bytes
+                        ))
+                    })?;
+                file = Some((filename, bytes));
+            }
+            "sourceLang" => source_lang = Some(read_text_field(&mut field, &name).await?),
+            "targetLang" => target_lang = Some(read_text_field(&mut field, &name).await?),
+            "style" => {
+                let value = read_text_field(&mut field, &name).await?;
+                if !value.trim().is_empty() {
+                    style = Some(Style::from_str(&value).map_err(|_| {
+                        ApiError::BadRequest(format!("unsupported style: {}", value.trim()))
+                    })?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (filename, bytes) = file.ok_or_else(|| ApiError::BadRequest("missing file".into()))?;
+    let format =
+        DocFormat::from_filename(&filename).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let document = documents::parse(format, &bytes, state.jobs.max_bytes)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    if document.units().is_empty() {
+        return Err(ApiError::BadRequest("no translatable text".into()));
+    }
+
+    let langs = resolve_langs(
+        &detect_sample(document.units()),
+        source_lang.as_deref(),
+        target_lang.as_deref(),
+    )?;
+    check_budget(&state, &caller)?;
+    let job = state
+        .jobs
+        .create(filename, format, langs.source, langs.target)
+        .map_err(|TooManyJobs(max)| {
+            ApiError::Busy(format!(
+                "too many document jobs in progress (max {}), retry later",
+                max
+            ))
+        })?;
+    info!(
+        job = %job.id,
+        format = format.as_str(),
+        units = document.units().len(),
+        "document job queued"
+    );
+
+    tokio::spawn(run_job(
+        state.clone(),
+        caller,
+        job.id.clone(),
+        document,
+        langs,
+        style,
+    ));
+    Ok((StatusCode::ACCEPTED, Json(JobResp::from(&job))))
+}
+
+/// `GET /api/tools/translate/documents/:id`
+pub async fn document_status(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<JobResp> {
+    let job = find_job(&state, &id)?;
+    Ok(Json(JobResp::from(&job)))
+}
+
+/// `GET /api/tools/translate/documents/:id/result`: the translated file,
+/// in the uploaded format.
+pub async fn document_result(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let job = find_job(&state, &id)?;
+    let Some(result) = job.result else {
+        return Err(ApiError::BadRequest(format!(
+            "job {} is not finished (status: {:?})",
+            job.id, job.status
+        )));
+    };
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        result_filename(&job.filename, job.target.as_str())
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, job.format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        result.as_ref().clone(),
+    )
+        .into_response())
+}
+
+fn find_job(state: &AppState, id: &str) -> Result<Job, ApiError> {
+    state
+        .jobs
+        .get(id)
+        .ok_or_else(|| ApiError::NotFound(format!("job not found: {}", id)))
+}
+
+/// The whole part, or `None` once it grows past `max` bytes.
+async fn read_field(field: &mut Field<'_>, max: usize) -> Result<Option<Vec<u8>>, ApiError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(bad_multipart)? {
+        if bytes.len() + chunk.len() > max {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+/// A short text part such as `targetLang`.
+async fn read_text_field(field: &mut Field<'_>, name: &str) -> Result<String, ApiError> {
+    let bytes = read_field(field, TEXT_FIELD_MAX_BYTES)
+        .await?
+        .ok_or_else(|| ApiError::BadRequest(format!("{} is too long", name)))?;
+    String::from_utf8(bytes).map_err(|_| ApiError::BadRequest(format!("{} is not UTF-8", name)))
+}
+
+fn bad_multipart(err: axum::extract::multipart::MultipartError) -> ApiError {
+    if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return ApiError::BadRequest("upload exceeds the size limit".into());
+    }
+    ApiError::BadRequest(format!("invalid multipart body: {}", err))
+}
+
+fn detect_sample(units: &[String]) -> String {
+    let mut sample = String::new();
+    for unit in units {
+        if sample.len() >= DETECT_SAMPLE_CHARS {
+            break;
+        }
+        sample.push_str(unit);
+        sample.push('\n');
+    }
+    sample
+}
+
+/// `report.docx` → `report.en.docx`, restricted to characters that are
+/// safe in a `Content-Disposition` header.
+fn result_filename(original: &str, target: &str) -> String {
+    let (stem, ext) = original.rsplit_once('.').unwrap_or((original, ""));
+    let stem: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.{}.{}", stem, target, ext)
+}
+
+async fn run_job(
+    state: AppState,
+    caller: Caller,
+    id: String,
+    document: Box<dyn Translatable>,
+    langs: ResolvedLangs,
+    style: Option<Style>,
+) {
+    let _permit = state.jobs.slots.clone().acquire_owned().await;
+    state
+        .jobs
+        .update(&id, |job| job.status = JobStatus::Running);
+
+    let outcome = match translate_units(&state, &caller, &id, document.units(), langs, style).await
+    {
+        Ok(translations) => document.render(&translations).map_err(|e| e.to_string()),
+        Err(message) => Err(message),
+    };
+    match outcome {
+        Ok(bytes) => {
+            info!(job = %id, bytes = bytes.len(), "document job done");
+            state.jobs.update(&id, |job| {
+                job.status = JobStatus::Done;
+                job.result = Some(bytes.into());
+            });
+        }
+        Err(message) => {
+            error!(job = %id, error = %message, "document job failed");
+            state.jobs.update(&id, |job| {
+                job.status = JobStatus::Failed;
+                job.error = Some(message);
+            });
+        }
+    }
+}
+
+/// Translates every unit, long ones piece by piece. Pieces go through the
+/// translation cache and are packed like a batch; progress counts distinct
+/// pieces.
+async fn translate_units(
+    state: &AppState,
+    caller: &Caller,
+    id: &str,
+    units: &[String],
+    langs: ResolvedLangs,
+    style: Option<Style>,
+) -> Result<Vec<String>, String> {
+    let scope = CacheScope {
+        style,
+        ..CacheScope::default()
+    };
+    let pieces: Vec<Vec<(&str, &str)>> = units
+        .iter()
+        .map(|unit| documents::chunks(unit, PIECE_MAX_CHARS))
+        .collect();
+
+    let mut translated: HashMap<String, String> = HashMap::new();
+    let mut misses: Vec<MissKey> = Vec::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+    for (text, _) in pieces.iter().flatten() {
+        if text.is_empty() || !seen.insert(*text) {
+            continue;
+        }
+        match cached_translation(state, langs, scope, text).await {
+            Some((translation, _)) => {
+                translated.insert(text.to_string(), translation);
+            }
+            None => misses.push(MissKey {
+                source: langs.source,
+                target: langs.target,
+                text: text.to_string(),
+            }),
+        }
+    }
+
+    let hits = translated.len();
+    state.jobs.update(id, |job| {
+        job.done = hits;
+        job.total = hits + misses.len();
+    });
+
+    let outcomes =
+        translate_misses_with_progress(state, caller, scope.options(), misses, |finished| {
+            state.jobs.update(id, |job| job.done += finished);
+        })
+        .await;
+    for (miss, outcome) in outcomes {
+        let result = outcome.map_err(|e| e.message)?;
+        remember(state, miss.source, miss.target, scope, &miss.text, &result).await;
+        translated.insert(miss.text, result.text);
+    }
+
+    Ok(pieces
+        .iter()
+        .map(|unit| {
+            unit.iter()
+                .map(|(text, sep)| {
+                    let translation = translated.get(*text).map_or(*text, String::as_str);
+                    format!("{}{}", translation, sep)
+                })
+                .collect()
+        })
+        .collect())
+}
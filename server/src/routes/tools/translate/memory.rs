@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use super::{parse_lang, resolve_langs};
+use crate::{
+    app::AppState,
+    error::{ApiError, ApiResult},
+    memory::{tmx, TranslationMemory},
+    routes::admin::Admin,
+};
+
+const DEFAULT_LIMIT: usize = 5;
+const MAX_LIMIT: usize = 50;
+const DEFAULT_MIN_SIMILARITY: f32 = 0.7;
+const LOOKUP_MAX_CHARS: usize = 2000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(in crate::routes::tools) struct LookupReq {
+    text: String,
+    #[serde(default)]
+    source_lang: Option<String>,
+    #[serde(default)]
+    target_lang: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Between 0 and 1; `1 - edit distance / length of the longer text`.
+    #[serde(default)]
+    min_similarity: Option<f32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(in crate::routes::tools) struct LookupResp {
+    detected_lang: &'static str,
+    target_lang: &'static str,
+    matches: Vec<MatchInfo>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MatchInfo {
+    source: String,
+    translation: String,
+    similarity: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// Unix seconds.
+    updated_at: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(in crate::routes::tools) struct ExportQuery {
+    #[serde(default)]
+    source_lang: Option<String>,
+    #[serde(default)]
+    target_lang: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(in crate::routes::tools) struct ImportResp {
+    imported: usize,
+    skipped: usize,
+}
+
+/// `POST /api/tools/translate/memory/lookup`: fuzzy matches from the
+/// translation memory, best first. Suggestions only; nothing is translated.
+pub async fn memory_lookup(
+    State(state): State<AppState>,
+    Json(req): Json<LookupReq>,
+) -> ApiResult<LookupResp> {
+    let memory = enabled(&state)?;
+    let text = req.text.trim();
+    if text.is_empty() {
+        return Err(ApiError::BadRequest("empty text".into()));
+    }
+    // Each candidate costs an edit distance quadratic in the length.
+    let chars = text.chars().count();
+    if chars > LOOKUP_MAX_CHARS {
+        return Err(ApiError::BadRequest(format!(
+            "text too long for lookup: {} characters (max {})",
+            chars, LOOKUP_MAX_CHARS
+        )));
+    }
+    let min_similarity = req.min_similarity.unwrap_or(DEFAULT_MIN_SIMILARITY);
+    if !(0.0..=1.0).contains(&min_similarity) {
+        return Err(ApiError::BadRequest(
+            "minSimilarity must be between 0 and 1".into(),
+        ));
+    }
+    let limit = req.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let langs = resolve_langs(text, req.source_lang.as_deref(), req.target_lang.as_deref())?;
+
+    let matches = memory
+        .fuzzy(langs.source, langs.target, text, min_similarity, limit)
+        .await
+        .map_err(memory_failure)?
+        .into_iter()
+        .map(|m| MatchInfo {
+            source: m.entry.source,
+            translation: m.entry.target,
+            similarity: m.similarity,
+            provider: m.entry.provider,
+            model: m.entry.model,
+            updated_at: m.entry.updated_at,
+        })
+        .collect();
+
+    Ok(Json(LookupResp {
+        detected_lang: langs.source.as_str(),
+        target_lang: langs.target.as_str(),
+        matches,
+    }))
+}
+
+/// `POST /api/tools/translate/memory/import`, body is a TMX document.
+/// Existing entries for the same source text are replaced, so this needs the
+/// admin token.
+pub async fn memory_import(
+    _: Admin,
+    State(state): State<AppState>,
+    body: String,
+) -> ApiResult<ImportResp> {
+    let memory = enabled(&state)?;
+    let imported =
+        tmx::parse(&body).map_err(|e| ApiError::BadRequest(format!("invalid TMX: {}", e)))?;
+    let skipped = imported.skipped;
+    let imported = memory
+        .store(imported.entries)
+        .await
+        .map_err(memory_failure)?;
+    info!(imported, skipped, "translation memory imported");
+    Ok(Json(ImportResp { imported, skipped }))
+}
+
+/// `GET /api/tools/translate/memory/export?sourceLang=&targetLang=`: the
+/// translation memory as a TMX 1.4 file. Needs the admin token, as it
+/// reveals every text anyone has translated.
+pub async fn memory_export(
+    _: Admin,
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let memory = enabled(&state)?;
+    let source = parse_lang(query.source_lang.as_deref())?;
+    let target = parse_lang(query.target_lang.as_deref())?;
+    let entries = memory
+        .export(source, target)
+        .await
+        .map_err(memory_failure)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-tmx+xml; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"translation_memory.tmx\"",
+            ),
+        ],
+        tmx::write(&entries),
+    )
+        .into_response())
+}
+
+fn enabled(state: &AppState) -> Result<&TranslationMemory, ApiError> {
+    state
+        .memory
+        .as_deref()
+        .ok_or_else(|| ApiError::BadRequest("translation memory is disabled".into()))
+}
+
+fn memory_failure(err: anyhow::Error) -> ApiError {
+    error!(error = %err, "translation memory failed");
+    ApiError::Internal("translation memory failed".into())
+}
@@ -0,0 +1,643 @@
+use std::{collections::HashMap, convert::Infallible, str::FromStr};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::request::Parts,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+
+use crate::{
+    app::AppState,
+    detect,
+    error::{ApiError, ApiResult},
+    glossary::{Glossary, Violation},
+    markdown::Document,
+    memory::TmEntry,
+    providers::{chain::Translated, Lang, ProviderError, Style, TranslateOptions},
+    usage,
+};
+
+mod back;
+mod batch;
+mod documents;
+mod memory;
+mod pack;
+mod stream;
+#[cfg(test)]
+mod tests;
+
+use pack::{into_api_error, translate_misses, MissKey};
+
+pub use batch::handle_batch;
+pub use documents::{document_result, document_status, handle_document};
+pub use memory::{memory_export, memory_import, memory_lookup};
+pub use stream::{handle_stream_get, handle_stream_post};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TranslateReq {
+    text: String,
+    #[serde(default)]
+    source_lang: Option<String>,
+    #[serde(default)]
+    target_lang: Option<String>,
+    /// Name of a configured glossary to enforce.
+    #[serde(default)]
+    glossary: Option<String>,
+    #[serde(default)]
+    format: Format,
+    /// Picks the prompt template; `None` uses the default one.
+    #[serde(default)]
+    style: Option<Style>,
+    /// Also translate the result back and score it against the original.
+    #[serde(default)]
+    back_translate: bool,
+    /// Between 0 and 1; segments scoring lower are flagged.
+    #[serde(default)]
+    back_translate_threshold: Option<f32>,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Format {
+    #[default]
+    Text,
+    /// Only prose is translated; code, URLs, HTML and front matter are kept
+    /// and the document structure is preserved. See `crate::markdown`.
+    Markdown,
+}
+
+impl Format {
+    fn as_str(self) -> &'static str {
+        match self {
+            Format::Text => "text",
+            Format::Markdown => "markdown",
+        }
+    }
+}
+
+/// Request options that change the translation, and so its cache key.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct CacheScope<'a> {
+    pub glossary: Option<&'a str>,
+    pub format: Format,
+    pub style: Option<Style>,
+}
+
+impl<'a> CacheScope<'a> {
+    /// Plain text in the default style without a glossary: the only
+    /// translations shared with the translation memory.
+    pub fn is_plain(&self) -> bool {
+        self.glossary.is_none() && self.format == Format::Text && self.style.is_none()
+    }
+
+    fn options(&self) -> TranslateOptions<'a> {
+        TranslateOptions {
+            style: self.style,
+            glossary: self.glossary,
+        }
+    }
+}
+
+/// Who is asking, for token accounting: the `X-Api-Key` header, or
+/// `anonymous`.
+#[derive(Clone)]
+pub(super) struct Caller(String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Infallible> {
+        let key = parts
+            .headers
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or(usage::ANONYMOUS);
+        Ok(Caller(key.to_string()))
+    }
+}
+
+impl Caller {
+    fn key(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct LanguageInfo {
+    code: &'static str,
+    name: &'static str,
+    native_name: &'static str,
+}
+
+#[derive(Serialize)]
+pub(super) struct LanguagesResp {
+    languages: Vec<LanguageInfo>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TranslateResp {
+    detected_lang: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+    target_lang: String,
+    translation: String,
+    provider: String,
+    cached: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    glossary: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    glossary_violations: Vec<Violation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    style: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    back_translation: Option<back::BackTranslation>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct GlossaryInfo {
+    name: String,
+    source_lang: Option<&'static str>,
+    target_lang: Option<&'static str>,
+    terms: usize,
+}
+
+#[derive(Serialize)]
+pub(super) struct GlossariesResp {
+    glossaries: Vec<GlossaryInfo>,
+}
+
+pub async fn handle(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<TranslateReq>,
+) -> ApiResult<TranslateResp> {
+    state.translator()?;
+    // Markdown keeps its surrounding whitespace; trailing newlines matter.
+    let text = match req.format {
+        Format::Text => req.text.trim(),
+        Format::Markdown => req.text.as_str(),
+    };
+    if text.trim().is_empty() {
+        return Err(ApiError::BadRequest("empty text".into()));
+    }
+    let threshold = req
+        .back_translate_threshold
+        .unwrap_or(back::DEFAULT_THRESHOLD);
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(ApiError::BadRequest(
+            "backTranslateThreshold must be between 0 and 1".into(),
+        ));
+    }
+
+    let document = (req.format == Format::Markdown).then(|| Document::parse(text));
+    let prose = match &document {
+        Some(document) => document.segments().collect::<Vec<_>>().join("\n"),
+        None => text.to_string(),
+    };
+    let langs = resolve_langs(
+        &prose,
+        req.source_lang.as_deref(),
+        req.target_lang.as_deref(),
+    )?;
+
+    let glossary_name = req
+        .glossary
+        .as_deref()
+        .map(str::trim)
+        .filter(|g| !g.is_empty());
+    let glossary = match glossary_name {
+        Some(name) => Some(select_glossary(&state, name, langs)?),
+        None => None,
+    };
+    let scope = CacheScope {
+        glossary: glossary_name,
+        format: req.format,
+        style: req.style,
+    };
+
+    let cached_hit = cached_translation(&state, langs, scope, text).await;
+    let cached = cached_hit.is_some();
+    let mut glossary_violations = Vec::new();
+    let (translation, provider) = match cached_hit {
+        Some(hit) => hit,
+        None => {
+            check_budget(&state, &caller)?;
+            let translated = match &document {
+                Some(document) => {
+                    let (translated, violations) =
+                        translate_markdown(&state, &caller, document, langs, glossary, scope)
+                            .await?;
+                    glossary_violations = violations;
+                    translated
+                }
+                None => {
+                    let masked = glossary.map(|g| g.mask(text));
+                    let input = masked.as_ref().map_or(text, |m| m.text.as_str());
+                    let mut result = call_provider(
+                        &state,
+                        &caller,
+                        input,
+                        Some(langs.source),
+                        langs.target,
+                        scope.options(),
+                    )
+                    .await
+                    .map_err(provider_failure)?;
+                    if let Some(masked) = &masked {
+                        let (restored, violations) = masked.restore(&result.text);
+                        glossary_violations = violations;
+                        result.text = restored;
+                    }
+                    result
+                }
+            };
+            // Output that broke the glossary is not cached, so the next
+            // request gets another chance.
+            if glossary_violations.is_empty() {
+                remember(&state, langs.source, langs.target, scope, text, &translated).await;
+            }
+            (translated.text, translated.provider)
+        }
+    };
+
+    let back_translation = if req.back_translate {
+        Some(
+            back::back_translate(
+                &state,
+                &caller,
+                langs,
+                req.format,
+                text,
+                &translation,
+                threshold,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    Ok(Json(TranslateResp {
+        detected_lang: langs.source.as_str().to_string(),
+        confidence: langs.confidence,
+        target_lang: langs.target.as_str().to_string(),
+        translation,
+        provider,
+        cached,
+        glossary: glossary_name.map(str::to_string),
+        glossary_violations,
+        style: req.style.map(|s| s.as_str()),
+        back_translation,
+    }))
+}
+
+/// Translates the prose segments of a Markdown document, packed like a
+/// batch, and splices them back. Also returns the glossary violations.
+async fn translate_markdown(
+    state: &AppState,
+    caller: &Caller,
+    document: &Document<'_>,
+    langs: ResolvedLangs,
+    glossary: Option<&Glossary>,
+    scope: CacheScope<'_>,
+) -> Result<(Translated, Vec<Violation>), ApiError> {
+    let segments: Vec<&str> = document.segments().collect();
+    let masks: Vec<_> = segments
+        .iter()
+        .map(|segment| glossary.map(|g| g.mask(segment)))
+        .collect();
+    let inputs: Vec<&str> = segments
+        .iter()
+        .zip(&masks)
+        .map(|(segment, masked)| masked.as_ref().map_or(*segment, |m| m.text.as_str()))
+        .collect();
+
+    let mut misses: Vec<MissKey> = Vec::new();
+    for input in &inputs {
+        if !misses.iter().any(|m| m.text == *input) {
+            misses.push(MissKey {
+                source: langs.source,
+                target: langs.target,
+                text: input.to_string(),
+            });
+        }
+    }
+
+    let mut answered_by: Option<(String, Option<String>)> = None;
+    let mut translated: HashMap<String, String> = HashMap::new();
+    for (miss, outcome) in translate_misses(state, caller, scope.options(), misses).await {
+        match outcome {
+            Ok(result) => {
+                answered_by.get_or_insert((result.provider, result.model));
+                translated.insert(miss.text, result.text);
+            }
+            Err(err) => return Err(into_api_error(err)),
+        }
+    }
+
+    let mut violations: Vec<Violation> = Vec::new();
+    let mut translations = Vec::with_capacity(inputs.len());
+    for (input, masked) in inputs.iter().zip(&masks) {
+        let output = translated.get(*input).cloned().unwrap_or_default();
+        let output = match masked {
+            Some(masked) => {
+                let (restored, found) = masked.restore(&output);
+                for violation in found {
+                    if !violations.iter().any(|v| v.term == violation.term) {
+                        violations.push(violation);
+                    }
+                }
+                restored
+            }
+            None => output,
+        };
+        translations.push(output);
+    }
+
+    // A document without prose needs no provider call; it is still cached
+    // under, and reported as, the first provider.
+    let (provider, model) = match answered_by {
+        Some(answered_by) => answered_by,
+        None => {
+            let first = state.translator()?.names().next().unwrap_or_default();
+            (first.to_string(), None)
+        }
+    };
+    let translated = Translated {
+        text: document.render(&translations),
+        provider,
+        model,
+        usage: None,
+    };
+    Ok((translated, violations))
+}
+
+pub async fn glossaries(State(state): State<AppState>) -> Json<GlossariesResp> {
+    let mut glossaries: Vec<GlossaryInfo> = state
+        .glossaries
+        .names()
+        .map(|(name, g)| GlossaryInfo {
+            name: name.to_string(),
+            source_lang: g.source_lang.map(|l| l.as_str()),
+            target_lang: g.target_lang.map(|l| l.as_str()),
+            terms: g.term_count(),
+        })
+        .collect();
+    glossaries.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(GlossariesResp { glossaries })
+}
+
+/// Looks up a glossary and checks it applies to the request's language pair.
+fn select_glossary<'a>(
+    state: &'a AppState,
+    name: &str,
+    langs: ResolvedLangs,
+) -> Result<&'a Glossary, ApiError> {
+    let glossary = state
+        .glossaries
+        .get(name)
+        .ok_or_else(|| ApiError::BadRequest(format!("unknown glossary: {}", name)))?;
+    let mismatch = glossary.source_lang.is_some_and(|l| l != langs.source)
+        || glossary.target_lang.is_some_and(|l| l != langs.target);
+    if mismatch {
+        return Err(ApiError::BadRequest(format!(
+            "glossary {} does not apply to {} -> {}",
+            name,
+            langs.source.as_str(),
+            langs.target.as_str()
+        )));
+    }
+    Ok(glossary)
+}
+
+/// Source and target language of a request, after auto-detection and
+/// defaulting.
+#[derive(Copy, Clone)]
+struct ResolvedLangs {
+    source: Lang,
+    /// Detector confidence; `None` when the caller named the source.
+    confidence: Option<f32>,
+    target: Lang,
+}
+
+fn resolve_langs(
+    text: &str,
+    source_lang: Option<&str>,
+    target_lang: Option<&str>,
+) -> Result<ResolvedLangs, ApiError> {
+    let (source, confidence) = match parse_lang(source_lang)? {
+        Some(lang) => (lang, None),
+        None => {
+            let detection = detect::detect(text);
+            debug!(
+                script = detection.script.map(|s| s.as_str()),
+                candidates = detection.candidates.len(),
+                "language detected"
+            );
+            match detection.top() {
+                Some(top) => (top.lang, Some(top.confidence)),
+                None => (Lang::En, None),
+            }
+        }
+    };
+
+    let target = match parse_lang(target_lang)? {
+        Some(lang) => lang,
+        None => default_target(source),
+    };
+
+    Ok(ResolvedLangs {
+        source,
+        confidence,
+        target,
+    })
+}
+
+/// Looks the text up under every provider in the chain. Keys are per
+/// provider, so a hit also tells us which backend produced the cached
+/// translation. Plain requests then fall back to an exact match in the
+/// translation memory. Returns `(translation, provider)`.
+async fn cached_translation(
+    state: &AppState,
+    langs: ResolvedLangs,
+    scope: CacheScope<'_>,
+    text: &str,
+) -> Option<(String, String)> {
+    for name in state.providers.iter().flat_map(|chain| chain.names()) {
+        let key = cache_key(
+            name,
+            langs.source.as_str(),
+            langs.target.as_str(),
+            scope,
+            text,
+        );
+        if let Some(value) = state.cache.get(&key).await {
+            return Some((value, name.to_string()));
+        }
+    }
+
+    let memory = state.memory.as_ref().filter(|_| scope.is_plain())?;
+    match memory.exact(langs.source, langs.target, text).await {
+        Ok(entry) => entry.map(|e| {
+            let provider = e.provider.unwrap_or_else(|| "memory".to_string());
+            (e.target, provider)
+        }),
+        Err(err) => {
+            warn!(error = %err, "translation memory lookup failed");
+            None
+        }
+    }
+}
+
+/// Caches a provider translation; plain requests are also recorded in the
+/// translation memory.
+async fn remember(
+    state: &AppState,
+    source: Lang,
+    target: Lang,
+    scope: CacheScope<'_>,
+    text: &str,
+    translated: &Translated,
+) {
+    let key = cache_key(
+        &translated.provider,
+        source.as_str(),
+        target.as_str(),
+        scope,
+        text,
+    );
+    state.cache.insert(key, translated.text.clone()).await;
+
+    let Some(memory) = state.memory.as_ref().filter(|_| scope.is_plain()) else {
+        return;
+    };
+    let entry = TmEntry {
+        source_lang: source,
+        target_lang: target,
+        source: text.to_string(),
+        target: translated.text.clone(),
+        provider: Some(translated.provider.clone()),
+        model: translated.model.clone(),
+        updated_at: crate::memory::now(),
+    };
+    if let Err(err) = memory.store(vec![entry]).await {
+        warn!(error = %err, "translation memory write failed");
+    }
+}
+
+/// Rejects the request once a daily token budget is used up. Checked only
+/// when a provider call is needed; cache hits stay free.
+fn check_budget(state: &AppState, caller: &Caller) -> Result<(), ApiError> {
+    state
+        .usage
+        .check(caller.key())
+        .map_err(|e| ApiError::QuotaExceeded(e.to_string()))
+}
+
+/// Translates through the provider chain and books the reported tokens to
+/// `caller`.
+async fn call_provider(
+    state: &AppState,
+    caller: &Caller,
+    text: &str,
+    source: Option<Lang>,
+    target: Lang,
+    options: TranslateOptions<'_>,
+) -> Result<Translated, ProviderError> {
+    // Handlers reject requests up front while translation is disabled.
+    let Ok(chain) = &state.providers else {
+        return Err(ProviderError::Unavailable("no provider configured".into()));
+    };
+    let result = chain.translate(text, source, target, options).await?;
+    if let Some(usage) = result.usage {
+        state.usage.record(
+            caller.key(),
+            &result.provider,
+            result.model.as_deref(),
+            usage,
+        );
+    }
+    Ok(result)
+}
+
+fn provider_failure(err: ProviderError) -> ApiError {
+    if let ProviderError::Unsupported(reason) = err {
+        return ApiError::BadRequest(format!("cannot translate: {}", reason));
+    }
+    error!(error = ?err, "translate failed");
+    ApiError::Internal("translation failed".into())
+}
+
+pub async fn languages() -> Json<LanguagesResp> {
+    let languages = Lang::ALL
+        .into_iter()
+        .map(|lang| LanguageInfo {
+            code: lang.as_str(),
+            name: lang.english_name(),
+            native_name: lang.native_name(),
+        })
+        .collect();
+    Json(LanguagesResp { languages })
+}
+
+/// `None`, empty and `auto` all mean "not specified"; anything else must be a
+/// supported BCP-47 code.
+fn parse_lang(value: Option<&str>) -> Result<Option<Lang>, ApiError> {
+    let value = match value.map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(v) if v.eq_ignore_ascii_case("auto") => return Ok(None),
+        Some(v) => v,
+    };
+    Lang::from_str(value)
+        .map(Some)
+        .map_err(|_| ApiError::BadRequest(format!("unsupported language: {}", value)))
+}
+
+fn default_target(source: Lang) -> Lang {
+    match source {
+        Lang::Zh => Lang::En,
+        _ => Lang::Zh,
+    }
+}
+
+/// Key prefix shared by every cache entry of `provider`.
+pub(crate) fn cache_prefix(provider: &str) -> String {
+    format!("{}:", provider)
+}
+
+/// `provider:digest`, so one provider's entries can be flushed by prefix.
+pub(crate) fn cache_key(
+    provider: &str,
+    source: &str,
+    target: &str,
+    scope: CacheScope<'_>,
+    text: &str,
+) -> String {
+    use std::fmt::Write as _;
+
+    let mut value = String::new();
+    let _ = write!(&mut value, "{}|{}|{}|", provider, source, target);
+    if let Some(glossary) = scope.glossary {
+        let _ = write!(&mut value, "glossary={}|", glossary);
+    }
+    // Plain text, the default, adds no `format=` field.
+    if scope.format != Format::Text {
+        let _ = write!(&mut value, "format={}|", scope.format.as_str());
+    }
+    if let Some(style) = scope.style {
+        let _ = write!(&mut value, "style={}|", style.as_str());
+    }
+    value.push_str(text);
+    let digest = md5::compute(value.as_bytes());
+    format!("{}{:x}", cache_prefix(provider), digest)
+}
@@ -0,0 +1,212 @@
+//! Packs many short texts into few provider calls. Used by the batch
+//! endpoint and by Markdown mode, which translates a document as a list of
+//! prose segments.
+
+use futures_util::{stream, StreamExt};
+use tracing::{error, warn};
+
+use super::{call_provider, provider_failure, Caller};
+use crate::{
+    app::AppState,
+    error::{ApiError, ApiErrorBody},
+    providers::{chain::Translated, Lang, ProviderError, TranslateOptions},
+};
+
+/// Provider calls in flight at once for a single request.
+const CONCURRENCY: usize = 4;
+/// Packs stay below these limits so each provider call fits comfortably in
+/// the model context and the provider timeout.
+pub(super) const PACK_MAX_CHARS: usize = 2000;
+const PACK_MAX_ITEMS: usize = 40;
+
+/// Identical texts with the same language pair are translated once.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct MissKey {
+    pub source: Lang,
+    pub target: Lang,
+    pub text: String,
+}
+
+pub(super) type PackOutcome = Vec<(MissKey, Result<Translated, ApiErrorBody>)>;
+
+/// Translates `misses`, packing texts that share a language pair. Results
+/// come back in no particular order.
+pub(super) async fn translate_misses(
+    state: &AppState,
+    caller: &Caller,
+    options: TranslateOptions<'_>,
+    misses: Vec<MissKey>,
+) -> PackOutcome {
+    translate_misses_with_progress(state, caller, options, misses, |_| {}).await
+}
+
+/// Like `translate_misses`, calling `progress` with the number of texts
+/// finished as each pack completes.
+pub(super) async fn translate_misses_with_progress(
+    state: &AppState,
+    caller: &Caller,
+    options: TranslateOptions<'_>,
+    misses: Vec<MissKey>,
+    mut progress: impl FnMut(usize),
+) -> PackOutcome {
+    let mut packs = stream::iter(pack(misses))
+        .map(|pack| translate_pack(state, caller, options, pack))
+        .buffer_unordered(CONCURRENCY);
+    let mut out = Vec::new();
+    while let Some(outcome) = packs.next().await {
+        progress(outcome.len());
+        out.extend(outcome);
+    }
+    out
+}
+
+/// Groups misses by language pair, then fills packs up to the size limits.
+fn pack(misses: Vec<MissKey>) -> Vec<Vec<MissKey>> {
+    let mut by_pair: Vec<((Lang, Lang), Vec<MissKey>)> = Vec::new();
+    for miss in misses {
+        let pair = (miss.source, miss.target);
+        match by_pair.iter_mut().find(|(p, _)| *p == pair) {
+            Some((_, group)) => group.push(miss),
+            None => by_pair.push((pair, vec![miss])),
+        }
+    }
+
+    let mut packs = Vec::new();
+    for (_, group) in by_pair {
+        let mut current: Vec<MissKey> = Vec::new();
+        let mut chars = 0;
+        for miss in group {
+            let len = miss.text.chars().count();
+            if !current.is_empty()
+                && (current.len() >= PACK_MAX_ITEMS || chars + len > PACK_MAX_CHARS)
+            {
+                packs.push(std::mem::take(&mut current));
+                chars = 0;
+            }
+            chars += len;
+            current.push(miss);
+        }
+        if !current.is_empty() {
+            packs.push(current);
+        }
+    }
+    packs
+}
+
+/// Sends a multi-item pack as one provider call with numbered segment
+/// markers. If the reply does not split back into the same number of
+/// segments, each item is retried on its own.
+async fn translate_pack(
+    state: &AppState,
+    caller: &Caller,
+    options: TranslateOptions<'_>,
+    pack: Vec<MissKey>,
+) -> PackOutcome {
+    let (source, target) = (pack[0].source, pack[0].target);
+
+    if pack.len() > 1 {
+        let packed = pack
+            .iter()
+            .enumerate()
+            .map(|(i, miss)| format!("{}\n{}", marker(i + 1), miss.text))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match call_provider(state, caller, &packed, Some(source), target, options).await {
+            Ok(result) => match unpack(&result.text, pack.len()) {
+                Some(segments) => {
+                    return pack
+                        .into_iter()
+                        .zip(segments)
+                        .map(|(miss, text)| {
+                            let translated = Translated {
+                                text,
+                                provider: result.provider.clone(),
+                                model: result.model.clone(),
+                                usage: None,
+                            };
+                            (miss, Ok(translated))
+                        })
+                        .collect();
+                }
+                None => warn!(
+                    items = pack.len(),
+                    "packed translation lost segment markers, translating items one by one"
+                ),
+            },
+            // One text the providers cannot take must not fail the others.
+            Err(ProviderError::Unsupported(reason)) => warn!(
+                items = pack.len(),
+                reason, "packed translation declined, translating items one by one"
+            ),
+            Err(err) => {
+                error!(error = ?err, items = pack.len(), "packed translate failed");
+                return pack
+                    .into_iter()
+                    .map(|miss| (miss, Err(item_error("Internal", "translation failed"))))
+                    .collect();
+            }
+        }
+    }
+
+    let mut out = Vec::with_capacity(pack.len());
+    for miss in pack {
+        let outcome = call_provider(state, caller, &miss.text, Some(source), target, options)
+            .await
+            .map_err(error_body);
+        out.push((miss, outcome));
+    }
+    out
+}
+
+fn marker(n: usize) -> String {
+    format!("[[[{}]]]", n)
+}
+
+/// Splits a packed reply on its `[[[n]]]` marker lines; `None` unless
+/// markers `1..=expected` all come back in order.
+fn unpack(reply: &str, expected: usize) -> Option<Vec<String>> {
+    let mut segments: Vec<String> = Vec::with_capacity(expected);
+    let mut current: Option<String> = None;
+    for line in reply.lines() {
+        if line.trim() == marker(segments.len() + current.is_some() as usize + 1) {
+            if let Some(done) = current.take() {
+                segments.push(done.trim().to_string());
+            }
+            current = Some(String::new());
+        } else if let Some(buf) = current.as_mut() {
+            buf.push_str(line);
+            buf.push('\n');
+        } else if !line.trim().is_empty() {
+            return None;
+        }
+    }
+    if let Some(done) = current.take() {
+        segments.push(done.trim().to_string());
+    }
+    (segments.len() == expected).then_some(segments)
+}
+
+/// Per-item form of the error the single-text path returns for `err`.
+fn error_body(err: ProviderError) -> ApiErrorBody {
+    match provider_failure(err) {
+        ApiError::BadRequest(message) => item_error("BadRequest", &message),
+        _ => item_error("Internal", "translation failed"),
+    }
+}
+
+/// Turns a per-item error back into the response of a request that fails
+/// as a whole, keeping client errors client errors.
+pub(super) fn into_api_error(body: ApiErrorBody) -> ApiError {
+    match body.code {
+        "BadRequest" => ApiError::BadRequest(body.message),
+        _ => ApiError::Internal(body.message),
+    }
+}
+
+pub(super) fn item_error(code: &'static str, message: &str) -> ApiErrorBody {
+    ApiErrorBody {
+        code,
+        message: message.to_string(),
+        details: None,
+    }
+}
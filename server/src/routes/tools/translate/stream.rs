@@ -0,0 +1,221 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures_util::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::error;
+
+use super::{
+    cached_translation, check_budget, provider_failure, remember, resolve_langs, CacheScope,
+    Caller, Format, ResolvedLangs, TranslateReq,
+};
+use crate::{
+    app::AppState,
+    error::ApiError,
+    providers::{chain::Translated, Chunk},
+};
+
+type EventStream = BoxStream<'static, Result<Event, Infallible>>;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MetaEvent {
+    detected_lang: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+    target_lang: &'static str,
+    provider: String,
+    cached: bool,
+}
+
+#[derive(Serialize)]
+struct DeltaEvent<'a> {
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct DoneEvent<'a> {
+    translation: &'a str,
+}
+
+#[derive(Serialize)]
+struct ErrorEvent {
+    code: &'static str,
+    message: String,
+}
+
+/// `GET /api/tools/translate/stream?text=...`, usable from `EventSource`.
+pub async fn handle_stream_get(
+    State(state): State<AppState>,
+    caller: Caller,
+    Query(req): Query<TranslateReq>,
+) -> Result<Sse<EventStream>, ApiError> {
+    stream_translation(state, caller, req).await
+}
+
+pub async fn handle_stream_post(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<TranslateReq>,
+) -> Result<Sse<EventStream>, ApiError> {
+    stream_translation(state, caller, req).await
+}
+
+/// Emits `meta`, then one `delta` per chunk, then `done` with the assembled
+/// translation (or `error` if the provider fails mid-stream). Validation and
+/// connection failures happen before the stream starts and are returned as
+/// regular JSON errors.
+async fn stream_translation(
+    state: AppState,
+    caller: Caller,
+    req: TranslateReq,
+) -> Result<Sse<EventStream>, ApiError> {
+    state.translator()?;
+    let text = req.text.trim().to_string();
+    if text.is_empty() {
+        return Err(ApiError::BadRequest("empty text".into()));
+    }
+    if req.format != Format::Text {
+        return Err(ApiError::BadRequest(
+            "streaming supports plain text only".into(),
+        ));
+    }
+    if req.back_translate {
+        return Err(ApiError::BadRequest(
+            "streaming does not support backTranslate".into(),
+        ));
+    }
+    // Glossary placeholders would show up in the deltas before they could
+    // be restored.
+    if req.glossary.is_some() {
+        return Err(ApiError::BadRequest(
+            "streaming does not support glossary".into(),
+        ));
+    }
+    let langs = resolve_langs(
+        &text,
+        req.source_lang.as_deref(),
+        req.target_lang.as_deref(),
+    )?;
+    let scope = CacheScope {
+        style: req.style,
+        ..CacheScope::default()
+    };
+
+    if let Some((translation, provider)) = cached_translation(&state, langs, scope, &text).await {
+        let events = vec![
+            sse_event("meta", &meta(langs, provider, true)),
+            sse_event("delta", &DeltaEvent { text: &translation }),
+            sse_event(
+                "done",
+                &DoneEvent {
+                    translation: &translation,
+                },
+            ),
+        ];
+        return Ok(Sse::new(stream::iter(events.into_iter().map(Ok)).boxed()));
+    }
+
+    check_budget(&state, &caller)?;
+    let upstream = state
+        .translator()?
+        .translate_stream(&text, Some(langs.source), langs.target, scope.options())
+        .await
+        .map_err(provider_failure)?;
+
+    let (tx, rx) = mpsc::channel(32);
+    let _ = tx.try_send(sse_event(
+        "meta",
+        &meta(langs, upstream.provider.clone(), false),
+    ));
+
+    // Drained in its own task so the translation still completes and lands
+    // in the cache if the client disconnects halfway.
+    tokio::spawn(async move {
+        let mut deltas = upstream.deltas;
+        let mut full = String::new();
+        let mut tokens = None;
+        while let Some(item) = deltas.next().await {
+            match item {
+                Ok(Chunk::Delta(delta)) => {
+                    full.push_str(&delta);
+                    let _ = tx
+                        .send(sse_event("delta", &DeltaEvent { text: &delta }))
+                        .await;
+                }
+                Ok(Chunk::Usage(usage)) => {
+                    state.usage.record(
+                        caller.key(),
+                        &upstream.provider,
+                        upstream.model.as_deref(),
+                        usage,
+                    );
+                    tokens = Some(usage);
+                }
+                Err(err) => {
+                    error!(error = ?err, "translate stream failed");
+                    let _ = tx
+                        .send(sse_event(
+                            "error",
+                            &ErrorEvent {
+                                code: "Internal",
+                                message: "translation failed".into(),
+                            },
+                        ))
+                        .await;
+                    return;
+                }
+            }
+        }
+        if !full.is_empty() {
+            let translated = Translated {
+                text: full.clone(),
+                provider: upstream.provider,
+                model: upstream.model,
+                usage: tokens,
+            };
+            remember(
+                &state,
+                langs.source,
+                langs.target,
+                scope,
+                &text,
+                &translated,
+            )
+            .await;
+        }
+        let _ = tx
+            .send(sse_event("done", &DoneEvent { translation: &full }))
+            .await;
+    });
+
+    let events = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(event), rx))
+    });
+    Ok(Sse::new(events.boxed()).keep_alive(KeepAlive::default()))
+}
+
+fn meta(langs: ResolvedLangs, provider: String, cached: bool) -> MetaEvent {
+    MetaEvent {
+        detected_lang: langs.source.as_str(),
+        confidence: langs.confidence,
+        target_lang: langs.target.as_str(),
+        provider,
+        cached,
+    }
+}
+
+fn sse_event<T: Serialize>(name: &'static str, data: &T) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|_| Event::default().event(name))
+}
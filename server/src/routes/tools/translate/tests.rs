@@ -0,0 +1,713 @@
+//! End-to-end tests of the translate endpoints over HTTP, against the mock
+//! provider and the fake OpenAI-compatible server; no network or keys.
+
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use crate::{
+    app::AppState,
+    glossary::GlossaryStore,
+    providers::{
+        dictionary::DictionaryProvider,
+        mock::{MockMode, MockProvider},
+        TranslateProvider,
+    },
+    routes,
+    test_support::{
+        serve, state, state_with, state_with_memory, state_without_providers, FakeOpenAi, Reply,
+        ADMIN_TOKEN,
+    },
+    usage::UsageTracker,
+};
+
+fn mock(mode: MockMode) -> Arc<dyn TranslateProvider> {
+    Arc::new(MockProvider::new("mock", mode))
+}
+
+struct App {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl App {
+    async fn start(state: AppState) -> Self {
+        Self {
+            base_url: serve(routes::router(state)).await,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn post(&self, path: &str, body: Value) -> (u16, Value) {
+        let resp = self
+            .client
+            .post(format!("{}{}", self.base_url, path))
+            .json(&body)
+            .send()
+            .await
+            .expect("request");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.expect("json body"))
+    }
+
+    async fn admin(&self, path: &str, body: Value) -> (u16, Value) {
+        let resp = self
+            .client
+            .post(format!("{}{}", self.base_url, path))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&body)
+            .send()
+            .await
+            .expect("request");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.expect("json body"))
+    }
+
+    /// Posts a multipart document upload; `parts` are `(name, filename,
+    /// content)`.
+    async fn upload(&self, parts: &[(&str, Option<&str>, &[u8])]) -> (u16, Value) {
+        const BOUNDARY: &str = "test-boundary";
+        let mut body = Vec::new();
+        for (name, filename, content) in parts {
+            body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+            let disposition = match filename {
+                Some(f) => format!("form-data; name=\"{}\"; filename=\"{}\"", name, f),
+                None => format!("form-data; name=\"{}\"", name),
+            };
+            body.extend_from_slice(
+                format!("Content-Disposition: {}\r\n\r\n", disposition).as_bytes(),
+            );
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+        let resp = self
+            .client
+            .post(format!("{}/api/tools/translate/documents", self.base_url))
+            .header(
+                "content-type",
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            )
+            .body(body)
+            .send()
+            .await
+            .expect("request");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    async fn translate(&self, body: Value) -> (u16, Value) {
+        self.post("/api/tools/translate", body).await
+    }
+
+    async fn usage(&self) -> Value {
+        self.client
+            .get(format!("{}/api/admin/usage", self.base_url))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("request")
+            .json()
+            .await
+            .expect("json body")
+    }
+}
+
+fn en_de(text: &str) -> Value {
+    json!({ "text": text, "sourceLang": "en", "targetLang": "de" })
+}
+
+#[tokio::test]
+async fn translates_then_serves_from_cache() {
+    let app = App::start(state(vec![mock(MockMode::Upper)])).await;
+
+    let (status, first) = app.translate(en_de("  hello world ")).await;
+    assert_eq!(status, 200);
+    assert_eq!(first["translation"], "HELLO WORLD");
+    assert_eq!(first["provider"], "mock");
+    assert_eq!(first["cached"], false);
+
+    let (_, second) = app.translate(en_de("hello world")).await;
+    assert_eq!(second["translation"], "HELLO WORLD");
+    assert_eq!(second["cached"], true);
+
+    let usage = app.usage().await;
+    assert_eq!(usage["total"]["calls"], 1);
+    assert_eq!(usage["total"]["totalTokens"], 22);
+}
+
+#[tokio::test]
+async fn rejects_invalid_requests() {
+    let app = App::start(state(vec![mock(MockMode::Echo)])).await;
+
+    let (status, body) = app.translate(en_de("   ")).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "BadRequest");
+
+    let (status, body) = app
+        .translate(json!({ "text": "hi", "targetLang": "xx" }))
+        .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["message"], "unsupported language: xx");
+
+    let (status, body) = app
+        .translate(json!({ "text": "hi", "glossary": "missing" }))
+        .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["message"], "unknown glossary: missing");
+}
+
+#[tokio::test]
+async fn unsupported_text_is_a_client_error() {
+    let fixtures = [("hello".to_string(), "Hallo".to_string())].into();
+    let provider = MockProvider::new("mock", MockMode::Fixture).with_fixtures(fixtures);
+    let app = App::start(state(vec![Arc::new(provider)])).await;
+
+    let (status, body) = app.translate(en_de("hello")).await;
+    assert_eq!((status, body["translation"].as_str()), (200, Some("Hallo")));
+
+    let (status, body) = app.translate(en_de("goodbye")).await;
+    assert_eq!(status, 400);
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("cannot translate"));
+
+    // Markdown segments and back-translation go through the batch path.
+    let mut req = en_de("# goodbye");
+    req["format"] = json!("markdown");
+    let (status, body) = app.translate(req).await;
+    assert_eq!((status, body["code"].as_str()), (400, Some("BadRequest")));
+    let mut req = en_de("hello");
+    req["backTranslate"] = json!(true);
+    let (status, body) = app.translate(req).await;
+    assert_eq!((status, body["code"].as_str()), (400, Some("BadRequest")));
+
+    // In a batch, only the item that cannot be translated fails.
+    let (status, body) = app
+        .post(
+            "/api/tools/translate/batch",
+            json!({ "texts": ["hello", "goodbye"], "sourceLang": "en", "targetLang": "de" }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    let results = &body["results"];
+    assert_eq!(results[0]["translation"], "Hallo");
+    assert_eq!(results[1]["error"]["code"], "BadRequest");
+}
+
+#[tokio::test]
+async fn glossary_terms_survive_translation() {
+    let glossaries = GlossaryStore::from_json(
+        r#"{ "product": { "sourceLang": "en", "targetLang": "zh",
+             "terms": [{ "source": "MagicBox", "target": "百宝箱" }] } }"#,
+    )
+    .unwrap();
+    let state = state_with(
+        vec![mock(MockMode::Upper)],
+        glossaries,
+        UsageTracker::new(None, None),
+    );
+    let app = App::start(state).await;
+
+    let (status, body) = app
+        .translate(json!({
+            "text": "open MagicBox now",
+            "sourceLang": "en",
+            "targetLang": "zh",
+            "glossary": "product",
+        }))
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["translation"], "OPEN 百宝箱 NOW");
+    assert_eq!(body["glossary"], "product");
+}
+
+#[tokio::test]
+async fn style_selects_prompt_and_cache_entry() {
+    let fake = FakeOpenAi::start(vec![]).await;
+    let app = App::start(state(vec![fake.provider("fake", 0)])).await;
+
+    let (_, plain) = app.translate(en_de("hello")).await;
+    assert_eq!(plain["cached"], false);
+
+    let mut formal = en_de("hello");
+    formal["style"] = json!("formal");
+    let (_, body) = app.translate(formal.clone()).await;
+    assert_eq!(body["cached"], false);
+    assert_eq!(body["style"], "formal");
+    let (_, body) = app.translate(formal).await;
+    assert_eq!(body["cached"], true);
+
+    let requests = fake.requests();
+    assert_eq!(requests.len(), 2);
+    let system = |i: usize| {
+        requests[i]["messages"][0]["content"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    assert!(!system(0).contains("formal"));
+    assert!(system(1).contains("formal, polite register"));
+}
+
+#[tokio::test]
+async fn retries_transient_upstream_errors() {
+    let fake = FakeOpenAi::start(vec![Reply::Status(503), Reply::Status(500)]).await;
+    let app = App::start(state(vec![fake.provider("fake", 2)])).await;
+
+    let (status, body) = app.translate(en_de("hello")).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["translation"], "HELLO");
+    assert_eq!(fake.calls(), 3);
+}
+
+#[tokio::test]
+async fn rejected_requests_are_not_retried_or_forwarded() {
+    let fake = FakeOpenAi::start(vec![Reply::Status(401)]).await;
+    let app = App::start(state(vec![fake.provider("fake", 3), mock(MockMode::Echo)])).await;
+
+    let (status, body) = app.translate(en_de("hello")).await;
+    assert_eq!(status, 500);
+    assert_eq!(body["code"], "Internal");
+    assert_eq!(fake.calls(), 1);
+}
+
+#[tokio::test]
+async fn long_retry_after_hands_over_to_fallback() {
+    let fake = FakeOpenAi::start(vec![Reply::StatusRetryAfter(429, 60)]).await;
+    let app = App::start(state(vec![
+        fake.provider("fake", 3),
+        mock(MockMode::Reverse),
+    ]))
+    .await;
+
+    let (status, body) = app.translate(en_de("abc")).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["provider"], "mock");
+    assert_eq!(body["translation"], "cba");
+    assert_eq!(fake.calls(), 1);
+}
+
+#[tokio::test]
+async fn circuit_opens_after_repeated_failures() {
+    let fake = FakeOpenAi::start(vec![Reply::Status(502); 3]).await;
+    let app = App::start(state(vec![fake.provider("fake", 0), mock(MockMode::Upper)])).await;
+
+    for text in ["one", "two", "three"] {
+        let (_, body) = app.translate(en_de(text)).await;
+        assert_eq!(body["provider"], "mock");
+    }
+    assert_eq!(fake.calls(), 3);
+
+    // The breaker is open now: the fake would answer, but is skipped.
+    let (_, body) = app.translate(en_de("four")).await;
+    assert_eq!(body["provider"], "mock");
+    assert_eq!(fake.calls(), 3);
+}
+
+#[tokio::test]
+async fn rejected_requests_count_as_healthy_answers() {
+    let script = [502, 502, 400, 502, 502].map(Reply::Status).to_vec();
+    let fake = FakeOpenAi::start(script).await;
+    let app = App::start(state(vec![fake.provider("fake", 0), mock(MockMode::Upper)])).await;
+
+    for text in ["one", "two", "three", "four", "five"] {
+        app.translate(en_de(text)).await;
+    }
+    assert_eq!(fake.calls(), 5);
+
+    // Without the reset on 400 this would be the fourth failure in a row.
+    let (_, body) = app.translate(en_de("six")).await;
+    assert_eq!(body["provider"], "fake");
+    assert_eq!(fake.calls(), 6);
+}
+
+#[tokio::test]
+async fn cache_keys_are_per_provider() {
+    let fake = FakeOpenAi::start(vec![Reply::Content("von fake".into())]).await;
+    let app = App::start(state(vec![fake.provider("fake", 0), mock(MockMode::Echo)])).await;
+
+    let (_, first) = app.translate(en_de("hello")).await;
+    assert_eq!(first["provider"], "fake");
+    let (_, again) = app.translate(en_de("hello")).await;
+    assert_eq!(
+        (again["provider"].as_str(), again["cached"].as_bool()),
+        (Some("fake"), Some(true))
+    );
+    assert_eq!(again["translation"], "von fake");
+}
+
+#[tokio::test]
+async fn invalidated_entries_go_back_to_the_provider() {
+    let fake = FakeOpenAi::start(vec![]).await;
+    let app = App::start(state_with_memory(vec![fake.provider("fake", 0)])).await;
+
+    let (_, first) = app.translate(en_de("hello")).await;
+    assert_eq!(first["cached"], false);
+    assert_eq!(fake.calls(), 1);
+
+    // Without the translation memory rows, the next request would be
+    // served from there instead.
+    let (status, removed) = app.admin("/api/admin/cache/delete", en_de("hello")).await;
+    assert_eq!(status, 200);
+    assert_eq!(removed, json!({ "removed": 1, "memoryRemoved": 1 }));
+    let (_, second) = app.translate(en_de("hello")).await;
+    assert_eq!(second["cached"], false);
+    assert_eq!(fake.calls(), 2);
+
+    let (status, removed) = app
+        .admin("/api/admin/cache/flush", json!({ "provider": "fake" }))
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(removed, json!({ "removed": 1, "memoryRemoved": 1 }));
+    let (_, third) = app.translate(en_de("hello")).await;
+    assert_eq!(third["cached"], false);
+    assert_eq!(fake.calls(), 3);
+
+    let (_, fourth) = app.translate(en_de("hello")).await;
+    assert_eq!(fourth["cached"], true);
+    assert_eq!(fake.calls(), 3);
+}
+
+#[tokio::test]
+async fn memory_import_and_export_need_the_admin_token() {
+    let app = App::start(state_with_memory(vec![mock(MockMode::Upper)])).await;
+    let tmx = r#"<tmx version="1.4"><header srclang="en"/><body>
+        <tu><tuv xml:lang="en"><seg>good morning</seg></tuv><tuv xml:lang="de"><seg>guten Morgen</seg></tuv></tu>
+    </body></tmx>"#;
+    let import = || {
+        app.client
+            .post(format!(
+                "{}/api/tools/translate/memory/import",
+                app.base_url
+            ))
+            .body(tmx)
+    };
+
+    let resp = import().send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+    let resp = import().bearer_auth("wrong").send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+    let (_, body) = app.translate(en_de("good morning")).await;
+    assert_eq!(body["translation"], "GOOD MORNING");
+
+    let resp = import().bearer_auth(ADMIN_TOKEN).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body, json!({ "imported": 1, "skipped": 0 }));
+
+    let (status, body) = app
+        .post(
+            "/api/tools/translate/memory/lookup",
+            json!({ "text": "good mornings", "sourceLang": "en", "targetLang": "de" }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["matches"][0]["translation"], "guten Morgen");
+
+    let (status, _) = app
+        .post(
+            "/api/tools/translate/memory/lookup",
+            json!({ "text": "a".repeat(2001), "sourceLang": "en", "targetLang": "de" }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    let export = || {
+        app.client.get(format!(
+            "{}/api/tools/translate/memory/export",
+            app.base_url
+        ))
+    };
+    assert_eq!(export().send().await.unwrap().status(), 401);
+    let resp = export().bearer_auth(ADMIN_TOKEN).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.text().await.unwrap().contains("guten Morgen"));
+}
+
+#[tokio::test]
+async fn batch_translates_duplicates_once() {
+    let app = App::start(state(vec![mock(MockMode::Upper)])).await;
+
+    let (status, body) = app
+        .post(
+            "/api/tools/translate/batch",
+            json!({ "texts": ["a", "b", "a", " "], "sourceLang": "en", "targetLang": "de" }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    let results = body["results"].as_array().unwrap();
+    let translations: Vec<&Value> = results.iter().map(|r| &r["translation"]).collect();
+    assert_eq!(
+        translations,
+        [&json!("A"), &json!("B"), &json!("A"), &Value::Null]
+    );
+    assert_eq!(results[3]["error"]["code"], "BadRequest");
+    // Two distinct texts, one packed provider call.
+    assert_eq!(app.usage().await["total"]["calls"], 1);
+}
+
+#[tokio::test]
+async fn stream_emits_deltas_and_fills_cache() {
+    let fake = FakeOpenAi::start(vec![]).await;
+    let app = App::start(state(vec![fake.provider("fake", 0)])).await;
+
+    let events = app
+        .client
+        .post(format!("{}/api/tools/translate/stream", app.base_url))
+        .json(&en_de("hello stream"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(events.contains("event: meta"));
+    assert_eq!(events.matches("event: delta").count(), 2);
+    assert!(events.contains(
+        r#"event: done
+data: {"translation":"HELLO STREAM"}"#
+    ));
+    assert_eq!(fake.requests()[0]["stream"], true);
+
+    let (_, body) = app.translate(en_de("hello stream")).await;
+    assert_eq!(body["cached"], true);
+    assert_eq!(app.usage().await["total"]["totalTokens"], 15);
+}
+
+#[tokio::test]
+async fn stream_rejects_glossary() {
+    let fake = FakeOpenAi::start(vec![]).await;
+    let app = App::start(state(vec![fake.provider("fake", 0)])).await;
+
+    let mut body = en_de("hello stream");
+    body["glossary"] = json!("brand");
+    let (status, body) = app.post("/api/tools/translate/stream", body).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["message"], "streaming does not support glossary");
+    assert_eq!(fake.calls(), 0);
+}
+
+#[tokio::test]
+async fn daily_budget_blocks_provider_calls_but_not_cache_hits() {
+    let state = state_with(
+        vec![mock(MockMode::Echo)],
+        GlossaryStore::default(),
+        UsageTracker::new(Some(10), None),
+    );
+    let app = App::start(state).await;
+
+    let (status, _) = app.translate(en_de("hello world")).await;
+    assert_eq!(status, 200);
+    let (status, body) = app.translate(en_de("something new")).await;
+    assert_eq!(status, 429);
+    assert_eq!(body["code"], "QuotaExceeded");
+    let (status, body) = app.translate(en_de("hello world")).await;
+    assert_eq!((status, body["cached"].as_bool()), (200, Some(true)));
+}
+
+#[tokio::test]
+async fn back_translation_flags_drift() {
+    let fixtures = [
+        ("Save\nOpen".to_string(), "Speichern\nÖffnen".to_string()),
+        ("Speichern".to_string(), "Save".to_string()),
+        ("Öffnen".to_string(), "Close".to_string()),
+    ]
+    .into();
+    let provider = MockProvider::new("mock", MockMode::Fixture).with_fixtures(fixtures);
+    let app = App::start(state(vec![Arc::new(provider)])).await;
+
+    let mut req = en_de("Save\nOpen");
+    req["backTranslate"] = json!(true);
+    let (status, body) = app.translate(req).await;
+    assert_eq!(status, 200);
+    let back = &body["backTranslation"];
+    assert_eq!(back["flagged"], 1);
+    assert_eq!(back["segments"][0]["backTranslation"], "Save");
+    assert_eq!(back["segments"][0]["flagged"], false);
+    assert_eq!(back["segments"][1]["backTranslation"], "Close");
+    assert_eq!(back["segments"][1]["flagged"], true);
+}
+
+#[tokio::test]
+async fn missing_provider_disables_only_translation() {
+    let app = App::start(state_without_providers("missing DEEPSEEK_API_KEY")).await;
+
+    let (status, body) = app.translate(en_de("hello")).await;
+    assert_eq!(status, 503);
+    assert_eq!(body["code"], "ProviderNotConfigured");
+    assert_eq!(
+        body["message"],
+        "translation is disabled: missing DEEPSEEK_API_KEY"
+    );
+    let (status, _) = app
+        .post(
+            "/api/tools/translate/batch",
+            json!({ "texts": ["a"], "targetLang": "de" }),
+        )
+        .await;
+    assert_eq!(status, 503);
+
+    let (status, body) = app
+        .post(
+            "/api/tools/hash",
+            json!({ "text": "abc", "algorithm": "md5" }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let get = |path: &str| app.client.get(format!("{}{}", app.base_url, path)).send();
+    let health: Value = get("/api/health").await.unwrap().json().await.unwrap();
+    assert_eq!(
+        health,
+        json!({ "ok": true, "disabled": ["translate", "translationMemory"] })
+    );
+    let capabilities: Value = get("/api/capabilities")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        capabilities["features"][0],
+        json!({ "name": "translate", "enabled": false, "reason": "missing DEEPSEEK_API_KEY" })
+    );
+    assert_eq!(capabilities["providers"], json!([]));
+}
+
+#[tokio::test]
+async fn dictionary_only_translation_is_degraded() {
+    let translate = |providers: Vec<Arc<dyn TranslateProvider>>| async move {
+        let app = App::start(state(providers)).await;
+        let url = format!("{}/api/capabilities", app.base_url);
+        let capabilities: Value = app
+            .client
+            .get(url)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        capabilities["features"][0].clone()
+    };
+    let dictionary = || -> Arc<dyn TranslateProvider> { Arc::new(DictionaryProvider::builtin()) };
+
+    let feature = translate(vec![dictionary()]).await;
+    assert_eq!(feature["enabled"], true);
+    let degraded = feature["degraded"].as_str().unwrap();
+    assert!(
+        degraded.starts_with("dictionary: phrase dictionary only"),
+        "{degraded}"
+    );
+    assert!(degraded.contains("en-zh ("), "{degraded}");
+
+    let feature = translate(vec![dictionary(), mock(MockMode::Upper)]).await;
+    assert!(feature.get("degraded").is_none(), "{feature}");
+}
+
+#[tokio::test]
+async fn document_upload_bounds_every_part() {
+    // The test job store allows 1 MiB per upload.
+    let app = App::start(state(vec![mock(MockMode::Upper)])).await;
+    let text: &[u8] = b"hello world";
+
+    let long_lang = vec![b'x'; 4096];
+    let (status, body) = app
+        .upload(&[
+            ("targetLang", None, &long_lang),
+            ("file", Some("a.txt"), text),
+        ])
+        .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["message"], "targetLang is too long");
+
+    let big = vec![b'a'; 1024 * 1024 + 1];
+    let (status, body) = app.upload(&[("file", Some("a.txt"), &big)]).await;
+    assert_eq!(status, 400, "{body}");
+
+    // Unknown parts still count against the body limit.
+    let padding = vec![b'a'; 2 * 1024 * 1024];
+    let (status, body) = app
+        .upload(&[("padding", None, &padding), ("file", Some("a.txt"), text)])
+        .await;
+    assert_eq!(status, 400);
+    assert!(
+        body["message"].as_str().unwrap().contains("limit"),
+        "{body}"
+    );
+}
+
+async fn finished_job(app: &App, id: &str) -> Value {
+    for _ in 0..100 {
+        let job: Value = app
+            .client
+            .get(format!(
+                "{}/api/tools/translate/documents/{}",
+                app.base_url, id
+            ))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] != "queued" && job["status"] != "running" {
+            return job;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("job {} did not finish", id);
+}
+
+#[tokio::test]
+async fn document_job_lifecycle() {
+    let app = App::start(state(vec![mock(MockMode::Upper)])).await;
+    let srt =
+        "1\n00:00:01,000 --> 00:00:02,000\nhello there\n\n2\n00:00:03,000 --> 00:00:04,000\n42\n";
+
+    let (status, job) = app
+        .upload(&[
+            ("sourceLang", None, b"en"),
+            ("targetLang", None, b"de"),
+            ("file", Some("talk.srt"), srt.as_bytes()),
+        ])
+        .await;
+    assert_eq!(status, 202, "{job}");
+    assert_eq!(job["format"], "srt");
+    let id = job["id"].as_str().unwrap();
+
+    let job = finished_job(&app, id).await;
+    assert_eq!(job["status"], "done", "{job}");
+    assert_eq!(job["progress"], json!({ "done": 1, "total": 1 }));
+    let result_url = job["resultUrl"].as_str().unwrap();
+    let resp = app
+        .client
+        .get(format!("{}{}", app.base_url, result_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["content-disposition"],
+        "attachment; filename=\"talk.de.srt\""
+    );
+    assert_eq!(
+        resp.text().await.unwrap(),
+        srt.replace("hello there", "HELLO THERE")
+    );
+
+    for path in ["missing", "missing/result"] {
+        let resp = app
+            .client
+            .get(format!(
+                "{}/api/tools/translate/documents/{}",
+                app.base_url, path
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 404);
+    }
+}
@@ -0,0 +1,241 @@
+//! Shared test helpers: serving a router on a free local port, a scripted
+//! stand-in for an OpenAI-compatible chat-completions server, and
+//! `AppState` builders that need no environment or network.
+
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use reqwest::header::HeaderMap;
+use serde_json::{json, Value};
+
+use crate::{
+    app::AppState,
+    cache::MemoryCache,
+    glossary::GlossaryStore,
+    jobs::JobStore,
+    memory::TranslationMemory,
+    providers::{
+        chain::ProviderChain,
+        openai_compat::{OpenAiCompatConfig, OpenAiCompatProvider, DEFAULT_PATH},
+        prompt::PromptTemplates,
+        retry::RetryPolicy,
+        TranslateProvider,
+    },
+    usage::UsageTracker,
+};
+
+pub const ADMIN_TOKEN: &str = "test-admin-token";
+
+/// Serves `router` on `127.0.0.1` and returns its base URL.
+pub async fn serve(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind test listener");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.expect("test server");
+    });
+    format!("http://{}", addr)
+}
+
+/// How the fake answers one request.
+#[derive(Clone, Debug)]
+pub enum Reply {
+    /// Upper-cases the text after `Text:\n` in the last message.
+    Upper,
+    /// Fixed content.
+    Content(String),
+    /// Error status with a short body.
+    Status(u16),
+    /// Error status with a `Retry-After` header, in seconds.
+    StatusRetryAfter(u16, u64),
+}
+
+struct FakeState {
+    script: Mutex<VecDeque<Reply>>,
+    requests: Mutex<Vec<Value>>,
+}
+
+/// In-process OpenAI-compatible server. Answers with the scripted replies
+/// in order, then `Reply::Upper` for every further request. Streaming
+/// requests get the content in two deltas, plus a usage chunk when asked.
+pub struct FakeOpenAi {
+    pub base_url: String,
+    state: Arc<FakeState>,
+}
+
+impl FakeOpenAi {
+    pub async fn start(script: Vec<Reply>) -> Self {
+        let state = Arc::new(FakeState {
+            script: Mutex::new(script.into()),
+            requests: Mutex::new(Vec::new()),
+        });
+        let router = Router::new()
+            .route(DEFAULT_PATH, post(chat))
+            .with_state(state.clone());
+        Self {
+            base_url: serve(router).await,
+            state,
+        }
+    }
+
+    /// Requests received so far.
+    pub fn calls(&self) -> usize {
+        self.state.requests.lock().unwrap().len()
+    }
+
+    /// Bodies of the requests received so far.
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// A provider pointed at this server, retrying up to `max_retries`
+    /// times with millisecond backoff.
+    pub fn provider(&self, name: &str, max_retries: u32) -> Arc<dyn TranslateProvider> {
+        Arc::new(OpenAiCompatProvider::new(OpenAiCompatConfig {
+            name: name.to_string(),
+            base_url: self.base_url.clone(),
+            path: DEFAULT_PATH.to_string(),
+            api_key: Some("test-key".to_string()),
+            model: "fake-model".to_string(),
+            headers: HeaderMap::new(),
+            timeout: Duration::from_secs(5),
+            retry: RetryPolicy {
+                max_retries,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(50),
+            },
+            prompts: Arc::new(PromptTemplates::default()),
+        }))
+    }
+}
+
+async fn chat(State(state): State<Arc<FakeState>>, Json(body): Json<Value>) -> Response {
+    state.requests.lock().unwrap().push(body.clone());
+    let reply = state
+        .script
+        .lock()
+        .unwrap()
+        .pop_front()
+        .unwrap_or(Reply::Upper);
+    let content = match reply {
+        Reply::Upper => {
+            let last = body["messages"]
+                .as_array()
+                .and_then(|m| m.last())
+                .and_then(|m| m["content"].as_str())
+                .unwrap_or_default();
+            let text = last.split_once("Text:\n").map_or(last, |(_, t)| t);
+            text.to_uppercase()
+        }
+        Reply::Content(content) => content,
+        Reply::Status(status) => return error_reply(status, None),
+        Reply::StatusRetryAfter(status, secs) => return error_reply(status, Some(secs)),
+    };
+    let usage = json!({ "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 });
+
+    if body["stream"].as_bool() != Some(true) {
+        return Json(json!({
+            "choices": [{ "message": { "role": "assistant", "content": content } }],
+            "usage": usage,
+        }))
+        .into_response();
+    }
+    let split = content
+        .char_indices()
+        .nth(content.chars().count() / 2)
+        .map_or(content.len(), |(i, _)| i);
+    let mut events = String::new();
+    for delta in [&content[..split], &content[split..]] {
+        let chunk = json!({ "choices": [{ "delta": { "content": delta } }] });
+        events.push_str(&format!("data: {}\n\n", chunk));
+    }
+    if body["stream_options"]["include_usage"].as_bool() == Some(true) {
+        let chunk = json!({ "choices": [], "usage": usage });
+        events.push_str(&format!("data: {}\n\n", chunk));
+    }
+    events.push_str("data: [DONE]\n\n");
+    ([(header::CONTENT_TYPE, "text/event-stream")], events).into_response()
+}
+
+fn error_reply(status: u16, retry_after: Option<u64>) -> Response {
+    let status = StatusCode::from_u16(status).expect("valid status");
+    let mut resp = (status, "scripted failure").into_response();
+    if let Some(secs) = retry_after {
+        resp.headers_mut()
+            .insert(header::RETRY_AFTER, secs.to_string().parse().unwrap());
+    }
+    resp
+}
+
+/// Fallback chain opening after three consecutive failures.
+pub fn chain(providers: Vec<Arc<dyn TranslateProvider>>) -> ProviderChain {
+    ProviderChain::new(providers, 3, Duration::from_secs(30))
+}
+
+/// In-memory everything: memory cache, no translation memory, no budgets,
+/// admin token `ADMIN_TOKEN`.
+pub fn state(providers: Vec<Arc<dyn TranslateProvider>>) -> AppState {
+    state_with(
+        providers,
+        GlossaryStore::default(),
+        UsageTracker::new(None, None),
+    )
+}
+
+pub fn state_with(
+    providers: Vec<Arc<dyn TranslateProvider>>,
+    glossaries: GlossaryStore,
+    usage: UsageTracker,
+) -> AppState {
+    build_state(Ok(chain(providers)), glossaries, usage, None)
+}
+
+/// Like `state`, with an in-memory translation memory.
+pub fn state_with_memory(providers: Vec<Arc<dyn TranslateProvider>>) -> AppState {
+    let memory = TranslationMemory::open(Path::new(":memory:")).expect("open translation memory");
+    build_state(
+        Ok(chain(providers)),
+        GlossaryStore::default(),
+        UsageTracker::new(None, None),
+        Some(memory),
+    )
+}
+
+/// Like `state`, but with translation disabled for `reason`.
+pub fn state_without_providers(reason: &str) -> AppState {
+    build_state(
+        Err(reason.to_string()),
+        GlossaryStore::default(),
+        UsageTracker::new(None, None),
+        None,
+    )
+}
+
+fn build_state(
+    providers: Result<ProviderChain, String>,
+    glossaries: GlossaryStore,
+    usage: UsageTracker,
+    memory: Option<TranslationMemory>,
+) -> AppState {
+    AppState::new(
+        providers,
+        Arc::new(MemoryCache::new(Duration::from_secs(300), 1000)),
+        glossaries,
+        JobStore::new(Duration::from_secs(60), 1, 2, 1024 * 1024),
+        memory,
+        usage,
+        Some(ADMIN_TOKEN.to_string()),
+    )
+}
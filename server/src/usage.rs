@@ -0,0 +1,302 @@
+//! Token accounting per provider/model and per caller API key, with optional
+//! daily budgets. Only keys on the configured list are counted on their
+//! own, so the ledger stays as small as that list; everyone else shares the
+//! `anonymous` bucket. Counts live in memory and restart from zero.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Mutex,
+};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use time::{Date, OffsetDateTime};
+
+use crate::providers::Usage;
+
+/// Callers that send no `X-Api-Key`, or one not on the list.
+pub const ANONYMOUS: &str = "anonymous";
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Totals {
+    /// Provider calls that reported usage.
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl Totals {
+    fn add(&mut self, usage: Usage) {
+        self.calls += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+        self.total_tokens += usage.total_tokens;
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BudgetExceeded {
+    #[error("daily token budget exhausted: {used}/{budget} used, resets at 00:00 UTC")]
+    Global { used: u64, budget: u64 },
+    #[error(
+        "daily token budget for this API key exhausted: {used}/{budget} used, resets at 00:00 UTC"
+    )]
+    Key { used: u64, budget: u64 },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// UTC day the `today` figures belong to.
+    pub day: String,
+    pub today: Totals,
+    pub daily_budget: Option<u64>,
+    pub daily_budget_per_key: Option<u64>,
+    pub total: Totals,
+    pub by_model: Vec<ModelTotals>,
+    pub by_key: Vec<KeyTotals>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelTotals {
+    pub provider: String,
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyTotals {
+    /// Masked API key.
+    pub key: String,
+    pub today: Totals,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+pub struct UsageTracker {
+    ledger: Mutex<Ledger>,
+    daily_budget: Option<u64>,
+    daily_budget_per_key: Option<u64>,
+    /// Keys accounted on their own.
+    api_keys: HashSet<KeyId>,
+}
+
+/// SHA-256 of an API key: distinct keys never share a budget, and the
+/// ledger never holds a key in clear.
+type KeyId = [u8; 32];
+
+struct Ledger {
+    day: Date,
+    today: Totals,
+    today_by_key: BTreeMap<KeyId, Totals>,
+    total: Totals,
+    by_model: BTreeMap<(String, Option<String>), Totals>,
+    /// Totals with the masked key shown in the report.
+    by_key: BTreeMap<KeyId, (String, Totals)>,
+}
+
+impl Ledger {
+    /// Starts a new day's counters once the UTC date changes.
+    fn roll(&mut self) {
+        let today = OffsetDateTime::now_utc().date();
+        if today != self.day {
+            self.day = today;
+            self.today = Totals::default();
+            self.today_by_key.clear();
+        }
+    }
+}
+
+impl UsageTracker {
+    pub fn new(daily_budget: Option<u64>, daily_budget_per_key: Option<u64>) -> Self {
+        Self {
+            ledger: Mutex::new(Ledger {
+                day: OffsetDateTime::now_utc().date(),
+                today: Totals::default(),
+                today_by_key: BTreeMap::new(),
+                total: Totals::default(),
+                by_model: BTreeMap::new(),
+                by_key: BTreeMap::new(),
+            }),
+            daily_budget,
+            daily_budget_per_key,
+            api_keys: HashSet::new(),
+        }
+    }
+
+    /// Keys accounted and budgeted on their own; any other key counts as
+    /// `anonymous`.
+    pub fn with_api_keys(mut self, keys: impl IntoIterator<Item = String>) -> Self {
+        self.api_keys = keys.into_iter().map(|k| key_id(&k)).collect();
+        self
+    }
+
+    /// The key `key` is accounted under, with its id.
+    fn account<'a>(&self, key: &'a str) -> (&'a str, KeyId) {
+        let id = key_id(key);
+        if self.api_keys.contains(&id) {
+            (key, id)
+        } else {
+            (ANONYMOUS, key_id(ANONYMOUS))
+        }
+    }
+
+    /// Fails once today's tokens reach a budget. A call already in flight
+    /// may overshoot it; the next request is the one rejected.
+    pub fn check(&self, key: &str) -> Result<(), BudgetExceeded> {
+        let mut ledger = self.ledger.lock().expect("usage ledger poisoned");
+        ledger.roll();
+        if let Some(budget) = self.daily_budget {
+            let used = ledger.today.total_tokens;
+            if used >= budget {
+                return Err(BudgetExceeded::Global { used, budget });
+            }
+        }
+        if let Some(budget) = self.daily_budget_per_key {
+            let (_, id) = self.account(key);
+            let used = ledger.today_by_key.get(&id).map_or(0, |t| t.total_tokens);
+            if used >= budget {
+                return Err(BudgetExceeded::Key { used, budget });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record(&self, key: &str, provider: &str, model: Option<&str>, usage: Usage) {
+        let (key, id) = self.account(key);
+        let mut ledger = self.ledger.lock().expect("usage ledger poisoned");
+        ledger.roll();
+        ledger.today.add(usage);
+        ledger.today_by_key.entry(id).or_default().add(usage);
+        ledger.total.add(usage);
+        ledger
+            .by_model
+            .entry((provider.to_string(), model.map(str::to_string)))
+            .or_default()
+            .add(usage);
+        ledger
+            .by_key
+            .entry(id)
+            .or_insert_with(|| (mask(key), Totals::default()))
+            .1
+            .add(usage);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let mut ledger = self.ledger.lock().expect("usage ledger poisoned");
+        ledger.roll();
+        Snapshot {
+            day: ledger.day.to_string(),
+            today: ledger.today,
+            daily_budget: self.daily_budget,
+            daily_budget_per_key: self.daily_budget_per_key,
+            total: ledger.total,
+            by_model: ledger
+                .by_model
+                .iter()
+                .map(|((provider, model), totals)| ModelTotals {
+                    provider: provider.clone(),
+                    model: model.clone(),
+                    totals: *totals,
+                })
+                .collect(),
+            by_key: {
+                let mut by_key: Vec<KeyTotals> = ledger
+                    .by_key
+                    .iter()
+                    .map(|(id, (key, totals))| KeyTotals {
+                        key: key.clone(),
+                        today: ledger.today_by_key.get(id).copied().unwrap_or_default(),
+                        totals: *totals,
+                    })
+                    .collect();
+                by_key.sort_by(|a, b| a.key.cmp(&b.key));
+                by_key
+            },
+        }
+    }
+}
+
+fn key_id(key: &str) -> KeyId {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// How an API key appears in the usage report: long keys keep their first
+/// and last four characters, shorter ones at most a quarter of theirs.
+/// Different keys may mask alike; the ledger tells them apart by `key_id`.
+fn mask(key: &str) -> String {
+    if key == ANONYMOUS {
+        return key.to_string();
+    }
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 12 {
+        let head: String = chars[..chars.len() / 4].iter().collect();
+        return format!("{}…", head);
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(total: u64) -> Usage {
+        Usage {
+            prompt_tokens: total,
+            completion_tokens: 0,
+            total_tokens: total,
+        }
+    }
+
+    #[test]
+    fn keys_alike_after_masking_have_separate_budgets() {
+        let (a, b) = ("sk-abcd-1111-wxyz", "sk-abcd-2222-wxyz");
+        let tracker = UsageTracker::new(None, Some(10)).with_api_keys([a.into(), b.into()]);
+        assert_eq!(mask(a), mask(b));
+
+        tracker.record(a, "mock", None, usage(10));
+        assert!(matches!(
+            tracker.check(a),
+            Err(BudgetExceeded::Key { used: 10, .. })
+        ));
+        assert!(tracker.check(b).is_ok());
+
+        tracker.record(b, "mock", None, usage(3));
+        let by_key = tracker.snapshot().by_key;
+        let mut totals: Vec<u64> = by_key.iter().map(|k| k.totals.total_tokens).collect();
+        totals.sort();
+        assert_eq!(totals, [3, 10]);
+        assert!(by_key.iter().all(|k| k.key == "sk-a…wxyz"));
+    }
+
+    #[test]
+    fn unlisted_keys_share_the_anonymous_bucket() {
+        let tracker = UsageTracker::new(None, Some(10)).with_api_keys(["listed".into()]);
+        tracker.record("made-up-1", "mock", None, usage(6));
+        tracker.record("made-up-2", "mock", None, usage(4));
+        assert!(matches!(
+            tracker.check("made-up-3"),
+            Err(BudgetExceeded::Key { used: 10, .. })
+        ));
+        assert!(tracker.check(ANONYMOUS).is_err());
+        assert!(tracker.check("listed").is_ok());
+
+        let by_key = tracker.snapshot().by_key;
+        assert_eq!(by_key.len(), 1);
+        assert_eq!(by_key[0].key, ANONYMOUS);
+    }
+
+    #[test]
+    fn short_keys_are_masked() {
+        assert_eq!(mask("secret12"), "se…");
+        assert_eq!(mask("abc"), "…");
+        assert_eq!(mask(ANONYMOUS), ANONYMOUS);
+    }
+}